@@ -0,0 +1,189 @@
+use image::{DynamicImage, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Basic tone and colour adjustments, applied together as one edit operation.
+///
+/// Exposure is in stops; every other value runs from -100 to 100 with 0 meaning unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjustments {
+    pub exposure: f32,
+    pub contrast: f32,
+    pub highlights: f32,
+    pub shadows: f32,
+    pub whites: f32,
+    pub blacks: f32,
+    pub temperature: f32,
+    pub tint: f32,
+    pub vibrance: f32,
+    pub saturation: f32,
+}
+
+/// One slider of the adjustments panel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Adjustment {
+    Exposure,
+    Contrast,
+    Highlights,
+    Shadows,
+    Whites,
+    Blacks,
+    Temperature,
+    Tint,
+    Vibrance,
+    Saturation,
+}
+
+impl Adjustment {
+    pub const ALL: [Adjustment; 10] = [
+        Adjustment::Exposure,
+        Adjustment::Contrast,
+        Adjustment::Highlights,
+        Adjustment::Shadows,
+        Adjustment::Whites,
+        Adjustment::Blacks,
+        Adjustment::Temperature,
+        Adjustment::Tint,
+        Adjustment::Vibrance,
+        Adjustment::Saturation,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Adjustment::Exposure => "Exposure",
+            Adjustment::Contrast => "Contrast",
+            Adjustment::Highlights => "Highlights",
+            Adjustment::Shadows => "Shadows",
+            Adjustment::Whites => "Whites",
+            Adjustment::Blacks => "Blacks",
+            Adjustment::Temperature => "Temperature",
+            Adjustment::Tint => "Tint",
+            Adjustment::Vibrance => "Vibrance",
+            Adjustment::Saturation => "Saturation",
+        }
+    }
+
+    pub fn range(self) -> RangeInclusive<f32> {
+        match self {
+            Adjustment::Exposure => -5.0..=5.0,
+            _ => -100.0..=100.0,
+        }
+    }
+
+    pub fn step(self) -> f32 {
+        match self {
+            Adjustment::Exposure => 0.05,
+            _ => 1.0,
+        }
+    }
+
+    pub fn get(self, adjustments: &Adjustments) -> f32 {
+        match self {
+            Adjustment::Exposure => adjustments.exposure,
+            Adjustment::Contrast => adjustments.contrast,
+            Adjustment::Highlights => adjustments.highlights,
+            Adjustment::Shadows => adjustments.shadows,
+            Adjustment::Whites => adjustments.whites,
+            Adjustment::Blacks => adjustments.blacks,
+            Adjustment::Temperature => adjustments.temperature,
+            Adjustment::Tint => adjustments.tint,
+            Adjustment::Vibrance => adjustments.vibrance,
+            Adjustment::Saturation => adjustments.saturation,
+        }
+    }
+
+    pub fn set(self, adjustments: &mut Adjustments, value: f32) {
+        let field = match self {
+            Adjustment::Exposure => &mut adjustments.exposure,
+            Adjustment::Contrast => &mut adjustments.contrast,
+            Adjustment::Highlights => &mut adjustments.highlights,
+            Adjustment::Shadows => &mut adjustments.shadows,
+            Adjustment::Whites => &mut adjustments.whites,
+            Adjustment::Blacks => &mut adjustments.blacks,
+            Adjustment::Temperature => &mut adjustments.temperature,
+            Adjustment::Tint => &mut adjustments.tint,
+            Adjustment::Vibrance => &mut adjustments.vibrance,
+            Adjustment::Saturation => &mut adjustments.saturation,
+        };
+        *field = value;
+    }
+
+    /// Formats `value` the way the panel shows it.
+    pub fn format(self, value: f32) -> String {
+        match self {
+            Adjustment::Exposure => format!("{:+.2} EV", value),
+            _ => format!("{:+.0}", value),
+        }
+    }
+}
+
+impl Adjustments {
+    pub fn is_identity(&self) -> bool {
+        *self == Adjustments::default()
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let mut pixels: Rgba32FImage = image.into_rgba32f();
+        for pixel in pixels.pixels_mut() {
+            let [red, green, blue, _] = &mut pixel.0;
+            [*red, *green, *blue] = self.apply_to_rgb([*red, *green, *blue]);
+        }
+        DynamicImage::ImageRgba32F(pixels).into_rgba8().into()
+    }
+
+    /// Adjusts one sRGB colour with components in `0.0..=1.0`.
+    fn apply_to_rgb(&self, rgb: [f32; 3]) -> [f32; 3] {
+        // Exposure and white balance work on linear light.
+        let gain = 2f32.powf(self.exposure);
+        let warmth = self.temperature / 100.0 * 0.2;
+        let tint = self.tint / 100.0 * 0.2;
+        let balance = [gain * (1.0 + warmth), gain * (1.0 - tint), gain * (1.0 - warmth)];
+        let mut rgb = std::array::from_fn::<f32, 3, _>(|channel| {
+            linear_to_srgb(srgb_to_linear(rgb[channel]) * balance[channel])
+        });
+
+        // Tone is shaped on luminance and carried over to the channels, so hues stay put.
+        let brightness = luminance(rgb);
+        let mut tone = brightness;
+        let black_point = -self.blacks / 100.0 * 0.1;
+        let white_point = 1.0 - self.whites / 100.0 * 0.1;
+        tone = (tone - black_point) / (white_point - black_point);
+        tone += self.shadows / 100.0 * 0.4 * (1.0 - tone).max(0.0).powi(2) * tone.clamp(0.0, 1.0).sqrt();
+        tone += self.highlights / 100.0 * 0.4 * tone.clamp(0.0, 1.0).powi(2) * (1.0 - tone).max(0.0).sqrt();
+        tone = (tone - 0.5) * (1.0 + self.contrast / 100.0) + 0.5;
+        if brightness > 0.0001 {
+            let ratio = tone.max(0.0) / brightness;
+            rgb = rgb.map(|value| value * ratio);
+        } else {
+            rgb = [tone.max(0.0); 3];
+        }
+
+        // Vibrance favours muted colours; saturation treats all alike.
+        let grey = luminance(rgb);
+        let colourfulness = rgb.iter().copied().fold(f32::MIN, f32::max) - rgb.iter().copied().fold(f32::MAX, f32::min);
+        let vibrance = 1.0 + self.vibrance / 100.0 * (1.0 - colourfulness.clamp(0.0, 1.0));
+        let saturation = (1.0 + self.saturation / 100.0) * vibrance;
+        rgb.map(|value| (grey + (value - grey) * saturation).clamp(0.0, 1.0))
+    }
+}
+
+fn luminance([red, green, blue]: [f32; 3]) -> f32 {
+    0.2126 * red + 0.7152 * green + 0.0722 * blue
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::app::file_ops::{write_store, SaveTicket};
+
+const ANNOTATIONS_FILE: &str = "annotations.json";
+
+/// Highest star rating a photo can be given.
+pub const MAX_RATING: u8 = 5;
+
+/// User supplied information about a photo that cannot be derived from the file itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub tags: BTreeSet<String>,
+    pub rating: u8,
+}
+
+impl Annotation {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.rating == 0
+    }
+}
+
+/// Tags and ratings for every annotated photo, keyed by path.
+///
+/// Kept apart from the catalog so rebuilding the catalog never loses user data.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Annotations {
+    photos: HashMap<PathBuf, Annotation>,
+}
+
+impl Annotations {
+    pub fn load() -> Self {
+        annotations_path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Annotation> {
+        self.photos.get(path)
+    }
+
+    pub fn rating(&self, path: &Path) -> u8 {
+        self.get(path).map_or(0, |annotation| annotation.rating)
+    }
+
+    pub fn add_tag(&mut self, path: &Path, tag: &str) {
+        self.photos.entry(path.to_path_buf()).or_default().tags.insert(tag.to_string());
+    }
+
+    pub fn remove_tag(&mut self, path: &Path, tag: &str) {
+        self.update(path, |annotation| {
+            annotation.tags.remove(tag);
+        });
+    }
+
+    pub fn set_rating(&mut self, path: &Path, rating: u8) {
+        let rating = rating.min(MAX_RATING);
+        self.photos.entry(path.to_path_buf()).or_default().rating = rating;
+        self.update(path, |_| {});
+    }
+
+    /// Carries the annotation of a file over to its new location after a move or rename.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if let Some(annotation) = self.photos.remove(from) {
+            self.photos.insert(to.to_path_buf(), annotation);
+        }
+    }
+
+    /// Replaces the whole annotation of a photo, as when restoring an earlier state.
+    pub fn set(&mut self, path: &Path, annotation: Option<Annotation>) {
+        match annotation.filter(|annotation| !annotation.is_empty()) {
+            Some(annotation) => {
+                self.photos.insert(path.to_path_buf(), annotation);
+            }
+            None => {
+                self.photos.remove(path);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.photos.remove(path);
+    }
+
+    fn update(&mut self, path: &Path, f: impl FnOnce(&mut Annotation)) {
+        if let Some(annotation) = self.photos.get_mut(path) {
+            f(annotation);
+            if annotation.is_empty() {
+                self.photos.remove(path);
+            }
+        }
+    }
+}
+
+pub async fn save_annotations(annotations: Annotations, ticket: SaveTicket) -> Result<(), String> {
+    write_annotations(&annotations, ticket).map_err(|err| err.to_string())
+}
+
+fn write_annotations(annotations: &Annotations, ticket: SaveTicket) -> io::Result<()> {
+    let path = annotations_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let contents = serde_json::to_vec_pretty(annotations).map_err(io::Error::other)?;
+    write_store(&path, &contents, ticket)
+}
+
+fn annotations_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("poer").join(ANNOTATIONS_FILE))
+}
@@ -1,19 +1,59 @@
 use iced::{Application, Command, Element, Settings, executor, Subscription, theme, Color};
-use iced::widget::{Column, Row, Scrollable, Container, Button, Text, Space, Image, TextInput, Checkbox, Radio};
-use iced::{Alignment, Length, Padding};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use iced::widget::scrollable::{self, RelativeOffset};
+use iced::widget::{Column, Row, Scrollable, Container, Button, Text, Space, Image, TextInput, Checkbox, Radio, ProgressBar, Slider, PickList};
+use iced::{keyboard, Alignment, Length, Padding, Rectangle, Size, Vector};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use image::DynamicImage;
 use std::cmp::Ordering;
 
-pub use app::photo_loader::{load_photos, Photo};
+pub use app::photo_loader::{Photo, PhotoId, SUPPORTED_EXTENSIONS};
 pub use app::ui_styles::{HeaderStyle, BackgroundStyle, ScrollableStyle};
+use app::adjust::{Adjustment, Adjustments};
+use app::annotations::{save_annotations, Annotations, MAX_RATING};
+use app::crop_view::{fit_to_ratio, AspectRatio, CropView, FULL_CROP};
+use app::curve_view::CurveEditor;
+use app::detail::{NoiseReduction, Sharpening};
+use app::edit::{replace_op, save_edits, CropRect, EditOp, EditStore};
+use app::export::{self, ExportEvent, ExportFormat, ExportJob, ExportSettings, ResampleFilter, ResizeMode, TEMPLATE_HELP};
+use app::file_ops::{self, FileResult, SaveTicket};
+use app::grid::{self, GridLayout, COLUMN_SPACING, GRID_PADDING, ROW_SPACING, THUMBNAIL_SIZES};
+use app::histogram::{Clipping, Histogram};
+use app::history::{Action, History, TrashedFile};
+use app::histogram_view::HistogramView;
+use app::library::LibrarySettings;
+use app::lut;
+use app::photo_card_style::PhotoCardStyle;
+use app::preset::{save_presets, Presets};
+use app::catalog::{load_catalog, save_catalog};
+use app::scanner::{self, KnownPhotos, ScanEvent, ScanProgress};
+use app::thumbnail::{self, ThumbnailCache, ThumbnailKey};
+use app::tone::{Curve, CurveChannel, Levels, ToneCurve};
+use app::transform::{self, Transform, TransformMode};
+use app::photo_view::{PhotoView, Zoom};
+use app::shortcuts::{self, Shortcut};
+use app::viewer::{self, CropSession, FullView, Loupe, LoupePatch, Preview, ViewerState};
+use app::watcher::{self, WatchEvent};
 use crate::app;
 
+/// Number of grid rows skipped by Page Up / Page Down.
+const PAGE_ROWS: usize = 3;
+/// Straighten slider range, in degrees either way.
+const MAX_STRAIGHTEN: f32 = 45.0;
+/// Initial window size.
+const WINDOW_SIZE: (u32, u32) = (1200, 800);
+/// Width of the history panel beside the grid or viewer.
+const HISTORY_PANEL_WIDTH: f32 = 240.0;
+/// Shortest time between refreshes of the grid while a scan is adding photos.
+const SCAN_REFILTER_INTERVAL: Duration = Duration::from_millis(500);
+
 pub fn main() -> iced::Result {
     PhotoOrganizer::run(Settings {
         window: iced::window::Settings {
-            size: (1200, 800),
+            size: WINDOW_SIZE,
             min_size: Some((800, 600)),
             ..Default::default()
         },
@@ -23,15 +63,83 @@ pub fn main() -> iced::Result {
 
 struct PhotoOrganizer {
     photos: Vec<Photo>,
+    photo_index: HashMap<PathBuf, usize>,
     filtered_photos: Vec<Photo>,
-    selected_photo: Option<usize>,
-    loading: bool,
-    row_count: usize,
+    /// Position of each filtered photo in `filtered_photos`.
+    filtered_index: HashMap<PhotoId, usize>,
+    /// The focused photo, which keyboard navigation and shift-click ranges start from.
+    selected_photo: Option<PhotoId>,
+    selection: HashSet<PhotoId>,
+    modifiers: keyboard::Modifiers,
+    scan: Option<ScanState>,
+    scan_count: u64,
+    grid: GridLayout,
     search_term: String,
     file_types: HashMap<String, bool>,
     size_filter: SizeFilter,
     sort_criteria: SortCriteria,
     sort_order: SortOrder,
+    library: LibrarySettings,
+    new_root_input: String,
+    /// Depth limits being typed, by library folder; applied when submitted.
+    depth_inputs: HashMap<PathBuf, String>,
+    thumbnails: ThumbnailCache,
+    viewer: Option<ViewerState>,
+    /// Whether the viewer marks pixels clipped to black and to white.
+    show_shadow_clipping: bool,
+    show_highlight_clipping: bool,
+    annotations: Annotations,
+    edits: EditStore,
+    presets: Presets,
+    preset_name_input: String,
+    /// Names of the imported LUTs.
+    luts: Vec<String>,
+    lut_input: String,
+    lut_error: Option<String>,
+    tag_input: String,
+    destination_input: String,
+    rename_input: String,
+    transform_mode: TransformMode,
+    /// Photos the user asked to delete, awaiting confirmation.
+    pending_delete: Option<Vec<PathBuf>>,
+    status_message: Option<String>,
+    /// Settings of the last export, offered again the next time.
+    export_settings: ExportSettings,
+    export_dialog: Option<ExportDialog>,
+    export: Option<ExportRun>,
+    export_count: u64,
+    history: History,
+    /// Number of applied steps the history is being undone or redone to.
+    history_target: Option<usize>,
+    /// Set while the files of a history step are being moved.
+    history_busy: bool,
+    show_history: bool,
+}
+
+/// The photos an export is being set up for, with the size fields as typed.
+struct ExportDialog {
+    photos: Vec<PhotoId>,
+    long_edge: String,
+    width: String,
+    height: String,
+    error: Option<String>,
+}
+
+struct ExportRun {
+    id: u64,
+    jobs: Arc<Vec<ExportJob>>,
+    settings: Arc<ExportSettings>,
+    results: Vec<FileResult>,
+}
+
+struct ScanState {
+    id: u64,
+    progress: ScanProgress,
+    known: KnownPhotos,
+    seen: HashSet<PathBuf>,
+    /// When the grid last caught up with the scanned photos, and whether any arrived since.
+    filtered_at: Instant,
+    unfiltered: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
@@ -57,14 +165,132 @@ pub enum SortOrder {
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    PhotosLoaded(Vec<Photo>),
-    PhotoSelected(usize),
-    PhotoDeselected,
+    Scan(u64, ScanEvent),
+    CancelScan,
+    CatalogSaved(Result<(), String>),
+    LibraryChanged(Vec<WatchEvent>),
+    ChangesProbed(Vec<Photo>),
+    ThumbnailLoaded(ThumbnailKey, Result<iced::widget::image::Handle, String>),
+    ThumbnailCachePruned(Result<(), String>),
+    PhotoClicked(PhotoId),
+    ModifiersChanged(keyboard::Modifiers),
+    GridScrolled(scrollable::Viewport),
+    WindowResized(Size),
+    ThumbnailSizeChanged(f32),
+    SelectAll,
+    SelectNone,
+    InvertSelection,
+    TagInput(String),
+    AddTagToSelection,
+    RemoveTagFromSelection,
+    RateSelection(u8),
+    DestinationInput(String),
+    RenameInput(String),
+    RenameSelection,
+    FilesRenamed(Vec<FileResult>),
+    MoveSelection,
+    ExportSelection,
+    ExportViewed,
+    ExportFormatSelected(ExportFormat),
+    ExportQualityChanged(u8),
+    ExportResizeSelected(ResizeMode),
+    ExportLongEdgeInput(String),
+    ExportWidthInput(String),
+    ExportHeightInput(String),
+    ExportFilterSelected(ResampleFilter),
+    ExportTemplateInput(String),
+    StartExport,
+    CloseExportDialog,
+    Export(u64, ExportEvent),
+    CancelExport,
+    DeleteSelection,
+    CancelDelete,
+    ConfirmDelete,
+    FilesMoved(Vec<FileResult>),
+    FilesDeleted(Vec<FileResult>),
+    TrashEmptied(Vec<FileResult>),
+    Undo,
+    Redo,
+    JumpToHistory(usize),
+    ToggleHistory,
+    /// Whether the move was undone, and its files or why it failed.
+    HistoryFilesMoved(bool, Result<Vec<FileResult>, String>),
+    HistoryFilesTrashed(Result<Vec<FileResult>, String>),
+    /// Trashed files were moved back, whose annotations and edits are restored with them.
+    HistoryFilesRestored(Vec<TrashedFile>, Result<Vec<FileResult>, String>),
+    TransformSelection(Transform),
+    TransformViewed(Transform),
+    LosslessTransformsToggled(bool),
+    FilesTransformed(Transform, TransformMode, Vec<FileResult>),
+    /// Whether the rotation or flip was undone, the edit stacks that go with the files,
+    /// and the files or why they could not be transformed.
+    HistoryFilesTransformed(bool, Vec<(PhotoId, Vec<EditOp>)>, Result<Vec<FileResult>, String>),
+    AnnotationsSaved(Result<(), String>),
+    AdjustmentChanged(Adjustment, f32),
+    AdjustmentReleased,
+    ResetAdjustments,
+    CurveChannelSelected(CurveChannel),
+    CurveChanged(Curve),
+    CurveReleased,
+    ResetCurve,
+    OpenLevels,
+    LevelsChanged(Levels),
+    ApplyLevels,
+    CancelLevels,
+    SharpeningChanged(Sharpening),
+    NoiseReductionChanged(NoiseReduction),
+    DetailReleased,
+    ResetDetail,
+    ToggleLoupe,
+    LoupeMoved(f32, f32),
+    OriginalLoaded(PhotoId, Result<Arc<DynamicImage>, String>),
+    LoupeRendered(PhotoId, Result<(LoupePatch, iced::widget::image::Handle), String>),
+    PresetNameInput(String),
+    SavePreset,
+    DeletePreset(String),
+    ApplyPreset(String),
+    ApplyPresetToSelection(String),
+    PresetsSaved(Result<(), String>),
+    PresetThumbnailsRendered(PhotoId, Vec<EditOp>, Vec<(String, iced::widget::image::Handle)>),
+    LutInput(String),
+    ImportLut,
+    LutImported(Result<String, String>),
+    LutSelected(String),
+    LutIntensityChanged(f32),
+    LutReleased,
+    RemoveLut,
+    RemoveEdit(usize),
+    RevertEdits,
+    EditsSaved(Result<(), String>),
+    StartCrop,
+    CropChanged(CropRect),
+    CropRatioSelected(AspectRatio),
+    CropCustomWidth(String),
+    CropCustomHeight(String),
+    StraightenChanged(f32),
+    StraightenReleased,
+    ResetCrop,
+    ApplyCrop,
+    CancelCrop,
+    CloseViewer,
+    ToggleClippingOverlay(Clipping),
+    ProxyLoaded(PhotoId, Result<(Arc<DynamicImage>, f32), String>),
+    FullViewRendered(PhotoId, Result<FullView, String>),
+    PreviewLoaded(PhotoId, Vec<EditOp>, Result<Preview, String>),
+    ViewerZoomChanged(Zoom, Vector),
+    ViewerPanned(Vector),
+    Shortcut(Shortcut),
     SearchInput(String),
     ToggleFileType(String),
     SelectSizeFilter(SizeFilter),
     SortCriteriaChanged(SortCriteria),
     ToggleSortOrder,
+    NewRootInput(String),
+    AddLibraryRoot,
+    RemoveLibraryRoot(usize),
+    ToggleLibraryRoot(usize),
+    LibraryRootDepthChanged(usize, String),
+    LibraryRootDepthSubmitted(usize),
 }
 
 impl Application for PhotoOrganizer {
@@ -75,25 +301,66 @@ impl Application for PhotoOrganizer {
 
     fn new(_flags: ()) -> (Self, Command<Message>) {
         let mut file_types = HashMap::new();
-        for ext in ["jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"] {
+        for ext in SUPPORTED_EXTENSIONS {
             file_types.insert(ext.to_string(), true);
         }
 
-        (
-            PhotoOrganizer {
-                photos: Vec::new(),
-                filtered_photos: Vec::new(),
-                selected_photo: None,
-                loading: true,
-                row_count: 0,
-                search_term: String::new(),
-                file_types,
-                size_filter: SizeFilter::All,
-                sort_criteria: SortCriteria::Name,
-                sort_order: SortOrder::Ascending,
-            },
-            Command::perform(load_photos(), Message::PhotosLoaded),
-        )
+        let library = LibrarySettings::load();
+
+        let mut organizer = PhotoOrganizer {
+            photos: Vec::new(),
+            photo_index: HashMap::new(),
+            filtered_photos: Vec::new(),
+            filtered_index: HashMap::new(),
+            selected_photo: None,
+            selection: HashSet::new(),
+            modifiers: keyboard::Modifiers::default(),
+            scan: None,
+            scan_count: 0,
+            grid: GridLayout::new(Size::new(WINDOW_SIZE.0 as f32, WINDOW_SIZE.1 as f32)),
+            search_term: String::new(),
+            file_types,
+            size_filter: SizeFilter::All,
+            sort_criteria: SortCriteria::Name,
+            sort_order: SortOrder::Ascending,
+            library,
+            new_root_input: String::new(),
+            depth_inputs: HashMap::new(),
+            thumbnails: ThumbnailCache::new(),
+            viewer: None,
+            show_shadow_clipping: false,
+            show_highlight_clipping: false,
+            annotations: Annotations::load(),
+            edits: EditStore::load(),
+            presets: Presets::load(),
+            preset_name_input: String::new(),
+            luts: lut::installed(),
+            lut_input: String::new(),
+            lut_error: None,
+            tag_input: String::new(),
+            destination_input: String::new(),
+            rename_input: String::new(),
+            transform_mode: TransformMode::Lossless,
+            pending_delete: None,
+            status_message: None,
+            export_settings: ExportSettings::default(),
+            export_dialog: None,
+            export: None,
+            export_count: 0,
+            history: History::default(),
+            history_target: None,
+            history_busy: false,
+            show_history: false,
+        };
+        organizer.upsert_photos(load_catalog());
+        organizer.apply_filters();
+        organizer.start_scan();
+
+        // Deleted photos can only be restored within the session that deleted them.
+        (organizer, Command::batch([
+            Command::perform(file_ops::empty_stale_trash(), Message::TrashEmptied),
+            Command::perform(thumbnail::prune_disk_cache(), Message::ThumbnailCachePruned),
+        ]))
     }
 
     fn title(&self) -> String {
@@ -102,16 +369,913 @@ impl Application for PhotoOrganizer {
 
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
-            Message::PhotosLoaded(photos) => {
-                self.photos = photos;
-                self.apply_filters();
-                self.loading = false;
+            Message::Scan(id, event) => {
+                if self.scan.as_ref().map(|scan| scan.id) != Some(id) {
+                    return Command::none();
+                }
+                match event {
+                    ScanEvent::Batch(photos, progress) => {
+                        let Some(scan) = &mut self.scan else {
+                            return Command::none();
+                        };
+                        scan.progress = progress;
+                        scan.seen.extend(photos.iter().map(|photo| photo.path.clone()));
+
+                        // Sorting the whole grid for every batch would make large scans quadratic.
+                        let changed = self.upsert_photos(photos);
+                        if let Some(scan) = &mut self.scan {
+                            scan.unfiltered |= changed;
+                            if scan.unfiltered && scan.filtered_at.elapsed() >= SCAN_REFILTER_INTERVAL {
+                                scan.unfiltered = false;
+                                scan.filtered_at = Instant::now();
+                                self.apply_filters();
+                            }
+                        }
+                    }
+                    ScanEvent::Finished => {
+                        if let Some(scan) = self.scan.take() {
+                            if self.retain_photos(|photo| scan.seen.contains(&photo.path)) || scan.unfiltered {
+                                self.apply_filters();
+                            }
+                            return self.persist_catalog();
+                        }
+                    }
+                }
             }
-            Message::PhotoSelected(index) => {
-                self.selected_photo = Some(index);
+            Message::CancelScan => {
+                if self.scan.take().is_some_and(|scan| scan.unfiltered) {
+                    self.apply_filters();
+                }
             }
-            Message::PhotoDeselected => {
-                self.selected_photo = None;
+            Message::CatalogSaved(result) => {
+                if let Err(err) = result {
+                    eprintln!("Failed to save photo catalog: {}", err);
+                }
+            }
+            Message::LibraryChanged(changes) => {
+                let mut to_probe = Vec::new();
+                let mut removed = false;
+                for change in changes {
+                    match change {
+                        WatchEvent::Removed(path) => {
+                            removed |= self.retain_photos(|photo| !photo.path.starts_with(&path));
+                        }
+                        WatchEvent::Added(path) | WatchEvent::Modified(path) => to_probe.push(path),
+                    }
+                }
+
+                let mut commands = Vec::new();
+                if removed {
+                    self.apply_filters();
+                    commands.push(self.persist_catalog());
+                }
+                if !to_probe.is_empty() {
+                    commands.push(Command::perform(watcher::probe_changes(to_probe, self.library.enabled_roots()), Message::ChangesProbed));
+                }
+                return Command::batch(commands);
+            }
+            Message::ThumbnailLoaded(key, result) => {
+                self.thumbnails.finish(key, result);
+            }
+            Message::ThumbnailCachePruned(result) => {
+                if let Err(err) = result {
+                    eprintln!("Failed to prune the thumbnail cache: {}", err);
+                }
+            }
+            Message::ChangesProbed(photos) => {
+                if let Some(scan) = &mut self.scan {
+                    scan.seen.extend(photos.iter().map(|photo| photo.path.clone()));
+                }
+                // Rotated or flipped photos are shown again with their new orientation.
+                let viewed = self.viewer.as_ref()
+                    .map(|viewer| viewer.photo.clone())
+                    .filter(|id| photos.iter().any(|photo| &photo.path == id));
+                if self.upsert_photos(photos) {
+                    self.apply_filters();
+                    let reload = viewed.map_or_else(Command::none, |id| self.reload_viewed(id));
+                    return Command::batch([self.persist_catalog(), reload]);
+                }
+            }
+            Message::PhotoClicked(id) => {
+                if self.modifiers.shift() {
+                    // Extend from the last clicked photo; with ctrl held the range is added to the selection.
+                    let Some(index) = self.filtered_index.get(&id).copied() else {
+                        return Command::none();
+                    };
+                    let anchor = self.focused_index().unwrap_or(index);
+                    if !self.modifiers.command() {
+                        self.selection.clear();
+                    }
+                    let range = &self.filtered_photos[anchor.min(index)..=anchor.max(index)];
+                    self.selection.extend(range.iter().map(|photo| photo.path.clone()));
+                } else if self.modifiers.command() {
+                    if !self.selection.remove(&id) {
+                        self.selection.insert(id.clone());
+                    }
+                    self.selected_photo = Some(id);
+                } else {
+                    self.select_only(id.clone());
+                    return self.open_viewer(id);
+                }
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+            }
+            Message::GridScrolled(viewport) => {
+                self.grid.offset = viewport.absolute_offset().y;
+            }
+            Message::WindowResized(size) => {
+                self.grid.window = size;
+            }
+            Message::ThumbnailSizeChanged(size) => {
+                self.grid.thumbnail_size = size;
+            }
+            Message::SelectAll => {
+                self.selection = self.filtered_photos.iter().map(|photo| photo.path.clone()).collect();
+            }
+            Message::SelectNone => {
+                self.selection.clear();
+            }
+            Message::InvertSelection => {
+                self.selection = self.filtered_photos.iter()
+                    .map(|photo| &photo.path)
+                    .filter(|id| !self.selection.contains(*id))
+                    .cloned()
+                    .collect();
+            }
+            Message::TagInput(tag) => {
+                self.tag_input = tag;
+            }
+            Message::AddTagToSelection | Message::RemoveTagFromSelection => {
+                let tag = self.tag_input.trim().to_string();
+                if tag.is_empty() {
+                    return Command::none();
+                }
+                self.tag_input.clear();
+                let count = photo_count(self.selected_paths().len());
+                return if matches!(message, Message::AddTagToSelection) {
+                    self.annotate_selection(format!("Tag {} “{}”", count, tag), |annotations, path| {
+                        annotations.add_tag(path, &tag);
+                    })
+                } else {
+                    self.annotate_selection(format!("Untag {} “{}”", count, tag), |annotations, path| {
+                        annotations.remove_tag(path, &tag);
+                    })
+                };
+            }
+            Message::RateSelection(rating) => {
+                let count = photo_count(self.selected_paths().len());
+                let label = if rating == 0 {
+                    format!("Clear rating of {}", count)
+                } else {
+                    format!("Rate {} {}", count, format_rating(rating))
+                };
+                return self.annotate_selection(label, |annotations, path| annotations.set_rating(path, rating));
+            }
+            Message::DestinationInput(destination) => {
+                self.destination_input = destination;
+            }
+            Message::RenameInput(name) => {
+                self.rename_input = name;
+            }
+            Message::RenameSelection => {
+                let paths = self.selected_paths();
+                let [path] = paths.as_slice() else {
+                    self.status_message = Some(String::from("Select a single photo to rename."));
+                    return Command::none();
+                };
+                if self.rename_input.trim().is_empty() {
+                    self.status_message = Some(String::from("Enter a new file name first."));
+                    return Command::none();
+                }
+                return Command::perform(file_ops::rename_file(path.clone(), self.rename_input.clone()), Message::FilesRenamed);
+            }
+            Message::MoveSelection => {
+                let destination = self.destination_input.trim();
+                if destination.is_empty() {
+                    self.status_message = Some(String::from("Enter a destination folder first."));
+                    return Command::none();
+                }
+                let destination = PathBuf::from(destination);
+                return Command::perform(file_ops::move_files(self.selected_paths(), destination), Message::FilesMoved);
+            }
+            Message::ExportSelection => {
+                self.open_export_dialog(self.selected_paths());
+            }
+            Message::ExportViewed => {
+                if let Some(viewer) = &self.viewer {
+                    self.open_export_dialog(vec![viewer.photo.clone()]);
+                }
+            }
+            Message::ExportFormatSelected(format) => {
+                self.export_settings.format = format;
+            }
+            Message::ExportQualityChanged(quality) => {
+                self.export_settings.quality = quality;
+            }
+            Message::ExportResizeSelected(resize) => {
+                self.export_settings.resize = resize;
+            }
+            Message::ExportLongEdgeInput(value) => {
+                if let Some(dialog) = &mut self.export_dialog {
+                    dialog.long_edge = value;
+                }
+            }
+            Message::ExportWidthInput(value) => {
+                if let Some(dialog) = &mut self.export_dialog {
+                    dialog.width = value;
+                }
+            }
+            Message::ExportHeightInput(value) => {
+                if let Some(dialog) = &mut self.export_dialog {
+                    dialog.height = value;
+                }
+            }
+            Message::ExportFilterSelected(filter) => {
+                self.export_settings.filter = filter;
+            }
+            Message::ExportTemplateInput(template) => {
+                self.export_settings.template = template;
+            }
+            Message::StartExport => {
+                self.start_export();
+            }
+            Message::CloseExportDialog => {
+                self.export_dialog = None;
+            }
+            Message::Export(id, event) => {
+                let Some(run) = self.export.as_mut().filter(|run| run.id == id) else {
+                    return Command::none();
+                };
+                match event {
+                    ExportEvent::Exported(result) => run.results.push(result),
+                    ExportEvent::Finished => {
+                        self.status_message = Some(summarize_results("Exported", &run.results));
+                        self.export = None;
+                    }
+                }
+            }
+            Message::CancelExport => {
+                if let Some(run) = self.export.take() {
+                    self.status_message = Some(format!(
+                        "Export cancelled. {}",
+                        summarize_results("Exported", &run.results)
+                    ));
+                }
+            }
+            Message::DeleteSelection => {
+                self.pending_delete = Some(self.selected_paths());
+            }
+            Message::CancelDelete => {
+                self.pending_delete = None;
+            }
+            Message::ConfirmDelete => {
+                // Deletes exactly the photos that were confirmed, even if the selection or
+                // filters changed since.
+                if let Some(paths) = self.pending_delete.take() {
+                    return Command::perform(file_ops::trash_files(paths), Message::FilesDeleted);
+                }
+            }
+            Message::FilesMoved(results) => {
+                self.status_message = Some(summarize_results("Moved", &results));
+                let moves = self.apply_moves(&results);
+                if moves.is_empty() {
+                    return self.files_changed();
+                }
+                let label = format!("Move {}", photo_count(moves.len()));
+                let record = self.record(label, Action::Move { moves });
+                return Command::batch([record, self.files_changed()]);
+            }
+            Message::FilesRenamed(results) => {
+                self.status_message = Some(summarize_results("Renamed", &results));
+                let moves = self.apply_moves(&results);
+                let Some((from, to)) = moves.first() else {
+                    return self.files_changed();
+                };
+                self.rename_input.clear();
+                let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                let label = format!("Rename {} to {}", file_name(from), file_name(to));
+                let record = self.record(label, Action::Move { moves });
+                return Command::batch([record, self.files_changed()]);
+            }
+            Message::FilesDeleted(results) => {
+                self.status_message = Some(summarize_results("Deleted", &results));
+                let files = results.iter()
+                    .filter_map(|(original, result)| {
+                        let trashed = result.as_ref().ok()?;
+                        Some(TrashedFile {
+                            original: original.clone(),
+                            trashed: trashed.clone(),
+                            annotation: self.annotations.get(original).cloned(),
+                            edits: self.edits.get(original).to_vec(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                self.remove_deleted(&results);
+                if files.is_empty() {
+                    return self.files_changed();
+                }
+                let label = format!("Delete {}", photo_count(files.len()));
+                let record = self.record(label, Action::Delete { files });
+                return Command::batch([record, self.files_changed()]);
+            }
+            Message::TrashEmptied(results) => {
+                for (path, result) in results {
+                    if let Err(err) = result {
+                        eprintln!("Failed to empty {} from the trash: {}", path.display(), err);
+                    }
+                }
+            }
+            Message::Undo | Message::Redo | Message::JumpToHistory(_) => {
+                if self.history_busy {
+                    return Command::none();
+                }
+                // An edit still in progress becomes a step of its own first.
+                let record = self.finish_edit();
+                let position = self.history.position();
+                self.history_target = match message {
+                    Message::Undo => position.checked_sub(1),
+                    Message::Redo => self.history.can_redo().then_some(position + 1),
+                    Message::JumpToHistory(target) => Some(target.min(self.history.steps().len())),
+                    _ => None,
+                };
+                return Command::batch([record, self.step_history()]);
+            }
+            Message::ToggleHistory => {
+                self.show_history = !self.show_history;
+                self.grid.side_panel = if self.show_history { HISTORY_PANEL_WIDTH } else { 0.0 };
+            }
+            Message::HistoryFilesMoved(undo, result) => {
+                self.history_busy = false;
+                let results = match result {
+                    Ok(results) => results,
+                    Err(err) => return self.step_failed(undo, err),
+                };
+                self.status_message = Some(summarize_results("Moved", &results));
+                self.apply_moves(&results);
+                return Command::batch([self.files_changed(), self.step_history()]);
+            }
+            Message::HistoryFilesTrashed(result) => {
+                self.history_busy = false;
+                let results = match result {
+                    Ok(results) => results,
+                    Err(err) => return self.step_failed(false, err),
+                };
+                self.status_message = Some(summarize_results("Deleted", &results));
+                self.remove_deleted(&results);
+                return Command::batch([self.files_changed(), self.step_history()]);
+            }
+            Message::HistoryFilesRestored(files, result) => {
+                self.history_busy = false;
+                let results = match result {
+                    Ok(results) => results,
+                    Err(err) => return self.step_failed(true, err),
+                };
+                for file in files {
+                    self.annotations.set(&file.original, file.annotation);
+                    self.edits.set(&file.original, file.edits);
+                }
+                self.status_message = Some(summarize_results("Restored", &results));
+                let restored = results.into_iter().filter_map(|(_, result)| result.ok()).collect();
+                return Command::batch([
+                    Command::perform(watcher::probe_changes(restored, self.library.enabled_roots()), Message::ChangesProbed),
+                    self.files_changed(),
+                    self.step_history(),
+                ]);
+            }
+            Message::TransformSelection(transform) => {
+                return self.transform_photos(self.selected_paths(), transform);
+            }
+            Message::TransformViewed(transform) => {
+                if let Some(id) = self.edited_photo() {
+                    return self.transform_photos(vec![id], transform);
+                }
+            }
+            Message::LosslessTransformsToggled(lossless) => {
+                self.transform_mode = if lossless { TransformMode::Lossless } else { TransformMode::Reencode };
+            }
+            Message::FilesTransformed(transform, mode, results) => {
+                self.status_message = Some(summarize_results("Rotated or flipped", &results));
+                let changed = results.into_iter()
+                    .filter_map(|(path, result)| result.ok().map(|_| path))
+                    .collect::<Vec<_>>();
+                if changed.is_empty() {
+                    return Command::none();
+                }
+                // Crops and rotations were drawn on the file as it was before.
+                let (before, after): (Vec<_>, Vec<_>) = changed.iter()
+                    .filter_map(|path| {
+                        let ops = self.edits.get(path);
+                        ops.iter().any(EditOp::is_framing).then(|| {
+                            ((path.clone(), ops.to_vec()), (path.clone(), transform.apply_to_edits(ops)))
+                        })
+                    })
+                    .unzip();
+                for (path, ops) in &after {
+                    self.edits.set(path, ops.clone());
+                }
+                let label = format!("{} {}", transform.label(), photo_count(changed.len()));
+                let action = Action::Transform { transform, mode, files: changed.clone(), before, after };
+                return Command::batch([self.record(label, action), self.transformed(changed)]);
+            }
+            Message::HistoryFilesTransformed(undo, edits, result) => {
+                self.history_busy = false;
+                let results = match result {
+                    Ok(results) => results,
+                    Err(err) => return self.step_failed(undo, err),
+                };
+                for (path, ops) in edits {
+                    self.edits.set(&path, ops);
+                }
+                self.status_message = Some(summarize_results("Rotated or flipped", &results));
+                let changed = results.into_iter().filter_map(|(_, result)| result.ok()).collect();
+                return Command::batch([self.transformed(changed), self.step_history()]);
+            }
+            Message::AnnotationsSaved(result) => {
+                if let Err(err) = result {
+                    eprintln!("Failed to save tags and ratings: {}", err);
+                }
+            }
+            Message::AdjustmentChanged(adjustment, value) => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, adjustment.label());
+                    let mut adjustments = self.edits.adjustments(&id);
+                    adjustment.set(&mut adjustments, value);
+                    self.edits.replace(&id, EditOp::Adjust(adjustments));
+                    // Saved once the slider is released.
+                    return self.load_preview(id);
+                }
+            }
+            Message::AdjustmentReleased => {
+                return self.commit_edits();
+            }
+            Message::ResetAdjustments => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Reset adjustments");
+                    self.edits.replace(&id, EditOp::Adjust(Adjustments::default()));
+                    return self.edits_changed(id);
+                }
+            }
+            Message::SharpeningChanged(sharpening) => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Sharpening");
+                    self.edits.replace(&id, EditOp::Sharpen(sharpening));
+                    // Detail edits only show in the loupe; saved once the slider is released.
+                    return self.load_loupe(id);
+                }
+            }
+            Message::NoiseReductionChanged(noise_reduction) => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Noise reduction");
+                    self.edits.replace(&id, EditOp::ReduceNoise(noise_reduction));
+                    return self.load_loupe(id);
+                }
+            }
+            Message::DetailReleased => {
+                return self.commit_edits();
+            }
+            Message::ResetDetail => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Reset detail");
+                    self.edits.replace(&id, EditOp::Sharpen(Sharpening::default()));
+                    self.edits.replace(&id, EditOp::ReduceNoise(NoiseReduction::default()));
+                    return Command::batch([self.load_loupe(id), self.commit_edits()]);
+                }
+            }
+            Message::ToggleLoupe => {
+                let Some(viewer) = &mut self.viewer else {
+                    return Command::none();
+                };
+                if viewer.loupe.take().is_some() {
+                    if !viewer.shows_full_resolution() {
+                        viewer.original = None;
+                    }
+                    return Command::none();
+                }
+                viewer.loupe = Some(Loupe::default());
+                let id = viewer.photo.clone();
+                return if viewer.original.is_some() { self.load_loupe(id) } else { self.load_original(id) };
+            }
+            Message::LoupeMoved(x, y) => {
+                if let Some(viewer) = &mut self.viewer
+                    && let Some(loupe) = &mut viewer.loupe
+                {
+                    loupe.center = (x, y);
+                    let id = viewer.photo.clone();
+                    return self.load_loupe(id);
+                }
+            }
+            Message::OriginalLoaded(id, result) => {
+                let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id) else {
+                    return Command::none();
+                };
+                viewer.loading_original = false;
+                if viewer.loupe.is_some() || viewer.shows_full_resolution() {
+                    match result {
+                        Ok(original) => {
+                            // Anything rendered from an earlier decode may be out of date.
+                            viewer.original = Some(original);
+                            viewer.full_view = None;
+                            if let Some(loupe) = &mut viewer.loupe {
+                                loupe.patch = None;
+                            }
+                            return Command::batch([self.load_loupe(id.clone()), self.load_full_view(id)]);
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to load photo at full resolution: {}", err);
+                            if let Some(loupe) = &mut viewer.loupe {
+                                loupe.error = Some(err);
+                            }
+                        }
+                    }
+                }
+            }
+            Message::LoupeRendered(id, result) => {
+                let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id) else {
+                    return Command::none();
+                };
+                let Some(loupe) = &mut viewer.loupe else {
+                    return Command::none();
+                };
+                loupe.rendering = false;
+                match result {
+                    // Patches of a photo that was decoded again since are rendered anew.
+                    Ok((patch, _)) if !viewer.original.as_ref().is_some_and(|original| Arc::ptr_eq(original, &patch.source)) => {
+                        loupe.render_pending = true;
+                    }
+                    Ok((patch, image)) => {
+                        loupe.patch = Some(patch);
+                        loupe.image = Some(image);
+                        loupe.error = None;
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to render loupe: {}", err);
+                        loupe.error = Some(err);
+                    }
+                }
+                if std::mem::take(&mut loupe.render_pending) {
+                    return self.load_loupe(id);
+                }
+            }
+            Message::PresetNameInput(name) => {
+                self.preset_name_input = name;
+            }
+            Message::SavePreset => {
+                let name = self.preset_name_input.trim();
+                if let Some(id) = self.edited_photo()
+                    && !name.is_empty()
+                    && self.edits.get(&id).iter().any(|op| !op.is_framing())
+                {
+                    self.presets.insert(name, self.edits.get(&id));
+                    self.preset_name_input.clear();
+                    return Command::batch([self.persist_presets(), self.load_preset_thumbnails(id)]);
+                }
+            }
+            Message::DeletePreset(name) => {
+                self.presets.remove(&name);
+                let thumbnails = match self.edited_photo() {
+                    Some(id) => self.load_preset_thumbnails(id),
+                    None => Command::none(),
+                };
+                return Command::batch([self.persist_presets(), thumbnails]);
+            }
+            Message::ApplyPreset(name) => {
+                if let Some(id) = self.edited_photo()
+                    && let Some(preset) = self.presets.get(&name)
+                {
+                    self.history.begin_edit(&id, &self.edits, format!("Apply preset {}", name));
+                    self.edits.set(&id, preset.apply_to(self.edits.get(&id)));
+                    return self.edits_changed(id);
+                }
+            }
+            Message::ApplyPresetToSelection(name) => {
+                let Some(preset) = self.presets.get(&name) else {
+                    return Command::none();
+                };
+                let mut before = Vec::new();
+                let mut after = Vec::new();
+                for path in self.selected_paths() {
+                    let ops = self.edits.get(&path).to_vec();
+                    let edited = preset.apply_to(&ops);
+                    if edited != ops {
+                        before.push((path.clone(), ops));
+                        after.push((path, edited));
+                    }
+                }
+                if after.is_empty() {
+                    return Command::none();
+                }
+                for (path, ops) in &after {
+                    self.edits.set(path, ops.clone());
+                }
+                self.status_message = Some(format!("Applied preset {} to {}.", name, photo_count(after.len())));
+                let label = format!("Apply preset {} to {}", name, photo_count(after.len()));
+                let record = self.record(label, Action::Edits { before, after });
+                return Command::batch([record, self.persist_edits()]);
+            }
+            Message::PresetsSaved(result) => {
+                if let Err(err) = result {
+                    eprintln!("Failed to save presets: {}", err);
+                }
+            }
+            Message::PresetThumbnailsRendered(id, edits, thumbnails) => {
+                let current = self.preview_edits(&id);
+                if edits == current
+                    && let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id)
+                {
+                    viewer.preset_thumbnails = thumbnails;
+                }
+            }
+            Message::LutInput(path) => {
+                self.lut_input = path;
+            }
+            Message::ImportLut => {
+                let path = self.lut_input.trim();
+                if !path.is_empty() {
+                    return Command::perform(lut::import(PathBuf::from(path)), Message::LutImported);
+                }
+            }
+            Message::LutImported(result) => match result {
+                Ok(name) => {
+                    self.luts = lut::installed();
+                    self.lut_input.clear();
+                    self.lut_error = None;
+                    if let Some(id) = self.edited_photo() {
+                        self.history.begin_edit(&id, &self.edits, format!("LUT {}", name));
+                        self.edits.replace(&id, EditOp::Lut { name, intensity: 1.0 });
+                        return self.edits_changed(id);
+                    }
+                }
+                Err(err) => self.lut_error = Some(format!("Could not import LUT: {}", err)),
+            },
+            Message::LutSelected(name) => {
+                if let Some(id) = self.edited_photo() {
+                    let intensity = self.edits.lut(&id).map_or(1.0, |(_, intensity)| intensity);
+                    self.history.begin_edit(&id, &self.edits, format!("LUT {}", name));
+                    self.edits.replace(&id, EditOp::Lut { name, intensity });
+                    return self.edits_changed(id);
+                }
+            }
+            Message::LutIntensityChanged(intensity) => {
+                if let Some(id) = self.edited_photo()
+                    && let Some((name, _)) = self.edits.lut(&id)
+                {
+                    self.history.begin_edit(&id, &self.edits, "LUT intensity");
+                    self.edits.replace(&id, EditOp::Lut { name, intensity });
+                    // Saved once the slider is released.
+                    return self.load_preview(id);
+                }
+            }
+            Message::LutReleased => {
+                return self.commit_edits();
+            }
+            Message::RemoveLut => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Remove LUT");
+                    let mut ops = self.edits.get(&id).to_vec();
+                    ops.retain(|op| !matches!(op, EditOp::Lut { .. }));
+                    self.edits.set(&id, ops);
+                    return self.edits_changed(id);
+                }
+            }
+            Message::CurveChannelSelected(channel) => {
+                if let Some(viewer) = &mut self.viewer {
+                    viewer.curve_channel = channel;
+                }
+            }
+            Message::CurveChanged(curve) => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Tone curve");
+                    self.set_curve(&id, curve);
+                    // Saved once the point is let go.
+                    return self.load_preview(id);
+                }
+            }
+            Message::CurveReleased => {
+                return self.commit_edits();
+            }
+            Message::ResetCurve => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Reset tone curve");
+                    self.set_curve(&id, Curve::default());
+                    return self.edits_changed(id);
+                }
+            }
+            Message::OpenLevels => {
+                if let Some(id) = self.edited_photo()
+                    && let Some(viewer) = &mut self.viewer
+                {
+                    viewer.levels = Some(self.edits.levels(&id));
+                }
+            }
+            Message::LevelsChanged(levels) => {
+                if let Some(viewer) = &mut self.viewer
+                    && viewer.levels.is_some()
+                {
+                    viewer.levels = Some(levels);
+                    let id = viewer.photo.clone();
+                    return self.load_preview(id);
+                }
+            }
+            Message::ApplyLevels => {
+                if let Some(viewer) = &mut self.viewer
+                    && let Some(levels) = viewer.levels.take()
+                {
+                    let id = viewer.photo.clone();
+                    self.history.begin_edit(&id, &self.edits, "Levels");
+                    self.edits.replace(&id, EditOp::Levels(levels));
+                    return self.edits_changed(id);
+                }
+            }
+            Message::CancelLevels => {
+                return self.cancel_levels();
+            }
+            Message::RemoveEdit(index) => {
+                if let Some(id) = self.edited_photo() {
+                    let label = self.edits.get(&id).get(index).map(|op| format!("Remove {}", op.label()));
+                    self.history.begin_edit(&id, &self.edits, label.unwrap_or_default());
+                    self.edits.remove_at(&id, index);
+                    return self.edits_changed(id);
+                }
+            }
+            Message::RevertEdits => {
+                if let Some(id) = self.edited_photo() {
+                    self.history.begin_edit(&id, &self.edits, "Revert to original");
+                    self.edits.remove(&id);
+                    return self.edits_changed(id);
+                }
+            }
+            Message::EditsSaved(result) => {
+                if let Err(err) = result {
+                    eprintln!("Failed to save edits: {}", err);
+                }
+            }
+            Message::StartCrop => {
+                if let Some(viewer) = &mut self.viewer {
+                    viewer.levels = None;
+                    viewer.crop = Some(CropSession::new(self.edits.get(&viewer.photo)));
+                    viewer.zoom = Zoom::Fit;
+                    let id = viewer.photo.clone();
+                    return self.load_preview(id);
+                }
+            }
+            Message::CropChanged(rect) => {
+                if let Some(crop) = self.crop_session() {
+                    crop.rect = rect;
+                }
+            }
+            Message::CropRatioSelected(ratio) => {
+                if let Some(crop) = self.crop_session() {
+                    crop.ratio = ratio;
+                }
+                self.fit_crop_to_ratio();
+            }
+            Message::CropCustomWidth(width) => {
+                if let Some(crop) = self.crop_session() {
+                    crop.custom_width = width;
+                }
+                self.fit_crop_to_ratio();
+            }
+            Message::CropCustomHeight(height) => {
+                if let Some(crop) = self.crop_session() {
+                    crop.custom_height = height;
+                }
+                self.fit_crop_to_ratio();
+            }
+            Message::StraightenChanged(degrees) => {
+                if let Some(crop) = self.crop_session() {
+                    crop.straighten = degrees;
+                }
+            }
+            Message::StraightenReleased => {
+                if let Some(viewer) = &self.viewer {
+                    return self.load_preview(viewer.photo.clone());
+                }
+            }
+            Message::ResetCrop => {
+                if let Some(crop) = self.crop_session() {
+                    crop.rect = FULL_CROP;
+                    crop.straighten = 0.0;
+                    crop.ratio = AspectRatio::Free;
+                }
+                if let Some(viewer) = &self.viewer {
+                    return self.load_preview(viewer.photo.clone());
+                }
+            }
+            Message::ApplyCrop => {
+                if let Some(viewer) = &mut self.viewer
+                    && let Some(crop) = viewer.crop.take()
+                {
+                    let id = viewer.photo.clone();
+                    self.history.begin_edit(&id, &self.edits, "Crop and straighten");
+                    self.edits.set(&id, crop.ops());
+                    return self.edits_changed(id);
+                }
+            }
+            Message::CancelCrop => {
+                return self.cancel_crop();
+            }
+            Message::CloseViewer => {
+                self.viewer = None;
+                return self.scroll_to_selection();
+            }
+            Message::ToggleClippingOverlay(clipping) => {
+                let shown = match clipping {
+                    Clipping::Shadows => &mut self.show_shadow_clipping,
+                    Clipping::Highlights => &mut self.show_highlight_clipping,
+                };
+                *shown = !*shown;
+                // Masks are only built while their overlay is on.
+                if *shown && let Some(viewer) = &self.viewer {
+                    return self.load_preview(viewer.photo.clone());
+                }
+            }
+            Message::ProxyLoaded(id, result) => {
+                if let Some(viewer) = &mut self.viewer
+                    && viewer.photo == id
+                {
+                    match result {
+                        Ok((proxy, scale)) => {
+                            viewer.proxy = Some(proxy);
+                            viewer.proxy_scale = scale;
+                            return self.load_preview(id);
+                        }
+                        Err(err) => viewer.error = Some(err),
+                    }
+                }
+            }
+            Message::PreviewLoaded(id, edits, result) => {
+                let current = self.preview_edits(&id);
+                let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id) else {
+                    return Command::none();
+                };
+                viewer.rendering = false;
+                // Previews rendered from an edit stack that has since changed are dropped for a fresh one.
+                if edits == current {
+                    match result {
+                        Ok(preview) => {
+                            viewer.preview = Some(preview);
+                            viewer.error = None;
+                        }
+                        Err(err) => viewer.error = Some(err),
+                    }
+                }
+                if std::mem::take(&mut viewer.render_pending) || edits != current {
+                    return self.load_preview(id);
+                }
+                return self.load_full_view(id);
+            }
+            Message::FullViewRendered(id, result) => {
+                let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id) else {
+                    return Command::none();
+                };
+                viewer.full_view_rendering = false;
+                match result {
+                    Ok(view) => {
+                        // Views of a photo that was decoded again since are dropped.
+                        if viewer.original.as_ref().is_some_and(|original| Arc::ptr_eq(original, &view.source)) {
+                            viewer.full_view = Some(view);
+                        }
+                        // Catches up with edits made during the render.
+                        return self.load_full_view(id);
+                    }
+                    Err(err) => {
+                        eprintln!("Failed to render photo at full resolution: {}", err);
+                        viewer.error = Some(err);
+                    }
+                }
+            }
+            Message::ViewerZoomChanged(zoom, offset) => {
+                if let Some(viewer) = &mut self.viewer {
+                    viewer.zoom = zoom;
+                    viewer.offset = offset;
+                    if !viewer.shows_full_resolution() {
+                        viewer.full_view = None;
+                        if viewer.loupe.is_none() {
+                            viewer.original = None;
+                        }
+                    }
+                    let id = viewer.photo.clone();
+                    return self.load_full_view(id);
+                }
+            }
+            Message::ViewerPanned(offset) => {
+                if let Some(viewer) = &mut self.viewer {
+                    viewer.offset = offset;
+                }
+            }
+            Message::Shortcut(Shortcut::Undo) => {
+                return self.update(Message::Undo);
+            }
+            Message::Shortcut(Shortcut::Redo) => {
+                return self.update(Message::Redo);
+            }
+            Message::Shortcut(shortcut) => {
+                return if self.viewer.is_some() {
+                    self.viewer_shortcut(shortcut)
+                } else {
+                    self.grid_shortcut(shortcut)
+                };
             }
             Message::SearchInput(term) => {
                 self.search_term = term;
@@ -138,261 +1302,2072 @@ impl Application for PhotoOrganizer {
                 };
                 self.apply_filters();
             }
+            Message::NewRootInput(input) => {
+                self.new_root_input = input;
+            }
+            Message::AddLibraryRoot => {
+                let input = self.new_root_input.trim();
+                if input.is_empty() {
+                    return Command::none();
+                }
+                let path = PathBuf::from(input);
+                if !path.is_dir() {
+                    self.status_message = Some(format!("{} is not a folder.", path.display()));
+                    return Command::none();
+                }
+                if self.library.add_root(path) {
+                    self.new_root_input.clear();
+                    return self.library_changed();
+                }
+            }
+            Message::RemoveLibraryRoot(index) => {
+                if let Some(root) = self.library.roots.get(index) {
+                    self.depth_inputs.remove(&root.path);
+                }
+                self.library.remove_root(index);
+                return self.library_changed();
+            }
+            Message::ToggleLibraryRoot(index) => {
+                if let Some(root) = self.library.roots.get_mut(index) {
+                    root.enabled = !root.enabled;
+                    return self.library_changed();
+                }
+            }
+            Message::LibraryRootDepthChanged(index, depth) => {
+                if let Some(root) = self.library.roots.get(index) {
+                    self.depth_inputs.insert(root.path.clone(), depth);
+                }
+            }
+            Message::LibraryRootDepthSubmitted(index) => {
+                if let Some(root) = self.library.roots.get_mut(index)
+                    && let Some(depth) = self.depth_inputs.remove(&root.path)
+                {
+                    let max_depth = if depth.trim().is_empty() {
+                        None
+                    } else if let Ok(depth) = depth.trim().parse() {
+                        Some(depth)
+                    } else {
+                        self.status_message = Some(format!("Depth must be a whole number, not \"{}\".", depth.trim()));
+                        return Command::none();
+                    };
+                    if root.max_depth != max_depth {
+                        root.max_depth = max_depth;
+                        return self.library_changed();
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let body: Element<'_, Message> = match (&self.export_dialog, &self.viewer) {
+            (Some(dialog), _) => create_export_dialog(self, dialog),
+            (None, Some(viewer)) => create_viewer(self, viewer),
+            (None, None) => self.view_library(),
+        };
+
+        let mut main = Row::new().push(body).height(Length::Fill);
+        if self.show_history {
+            main = main.push(create_history_panel(&self.history));
+        }
+
+        let mut content = Column::new().push(create_header(self));
+        if let Some(run) = &self.export {
+            content = content.push(create_export_status(run));
+        }
+        content
+            .push(main)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let roots = self.library.enabled_roots();
+        let scan = match &self.scan {
+            Some(scan) => scanner::scan(scan.id, roots.clone(), Arc::clone(&scan.known))
+                .with(scan.id)
+                .map(|(id, event)| Message::Scan(id, event)),
+            None => Subscription::none(),
+        };
+
+        let export = match &self.export {
+            Some(run) => export::export(run.id, Arc::clone(&run.jobs), Arc::clone(&run.settings))
+                .with(run.id)
+                .map(|(id, event)| Message::Export(id, event)),
+            None => Subscription::none(),
+        };
+
+        Subscription::batch([
+            scan,
+            export,
+            watcher::watch(roots).map(Message::LibraryChanged),
+            shortcuts::shortcuts().map(Message::Shortcut),
+            shortcuts::modifiers().map(Message::ModifiersChanged),
+            grid::window_resizes().map(Message::WindowResized),
+            self.thumbnails
+                .subscription(&self.filtered_photos[self.visible_photos()])
+                .map(|(key, result)| Message::ThumbnailLoaded(key, result)),
+        ])
+    }
+}
+
+fn create_header(app: &PhotoOrganizer) -> Container<'static, Message> {
+    let title = Text::new("POER")
+        .size(28)
+        .style(theme::Text::Color(Color::from_rgb(0.2, 0.5, 0.9)));
+
+    let subtitle = Text::new("Photo Organizer & Editor")
+        .size(14)
+        .style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)));
+
+    let header_content = Row::new()
+        .push(
+            Column::new()
+                .push(title)
+                .push(subtitle)
+                .spacing(2)
+        )
+        .push(Space::with_width(Length::Fill))
+        .push(Button::new(Text::new("↶ Undo")).on_press_maybe(app.history.can_undo().then_some(Message::Undo)))
+        .push(Button::new(Text::new("↷ Redo")).on_press_maybe(app.history.can_redo().then_some(Message::Redo)))
+        .push(Button::new(Text::new(if app.show_history { "Hide history" } else { "History" })).on_press(Message::ToggleHistory))
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .padding(Padding::new(20.0));
+
+    Container::new(header_content)
+        .width(Length::Fill)
+        .height(Length::Shrink)
+        .style(theme::Container::Custom(Box::new(HeaderStyle)))
+}
+
+fn create_filters<'a>(app: &'a PhotoOrganizer) -> Container<'a, Message> {
+    let search_input = Text::new("Search:")
+        .size(14)
+        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)));
+
+    let search_field = TextInput::new("Search by filename or tag...", &app.search_term)
+        .on_input(Message::SearchInput)
+        .padding(Padding::new(8.0));
+
+    let mut file_type_filters = Row::new().spacing(10);
+    for (ext, &enabled) in &app.file_types {
+        let ext_clone = ext.clone();
+        file_type_filters = file_type_filters.push(
+            Checkbox::new(ext, enabled, move |_| Message::ToggleFileType(ext_clone.clone()))
+        );
+    }
+
+    let size_filters = Row::new().spacing(10)
+        .push(Text::new("Size:").size(14))
+        .push(
+            Radio::new("All", SizeFilter::All, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::All))
+        )
+        .push(
+            Radio::new("Small (<100K)", SizeFilter::Small, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::Small))
+        )
+        .push(
+            Radio::new("Medium (100K-500K)", SizeFilter::Medium, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::Medium))
+        )
+        .push(
+            Radio::new("Large (>500K)", SizeFilter::Large, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::Large))
+        );
+
+    let filters_column = Column::new()
+        .push(Row::new().push(search_input).push(search_field).spacing(10))
+        .push(file_type_filters)
+        .push(size_filters)
+        .push(create_sorting_controls(app))
+        .push(create_library_controls(app))
+        .spacing(15)
+        .padding(Padding::new(20.0));
+
+    Container::new(filters_column)
+        .width(Length::Fill)
+        .style(theme::Container::Custom(Box::new(BackgroundStyle)))
+}
+
+fn create_loading_view(progress: &ScanProgress) -> Element<'static, Message> {
+    let loading_text = Text::new(format!("Loading photos... {} found so far", progress.seen))
+        .size(16)
+        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)));
+
+    Container::new(loading_text)
+        .width(iced::Length::Fill)
+        .height(iced::Length::Fill)
+        .align_x(iced::alignment::Horizontal::Center)
+        .align_y(iced::alignment::Vertical::Center)
+        .into()
+}
+
+fn create_scan_status(progress: &ScanProgress) -> Container<'static, Message> {
+    let status = Text::new(format!(
+        "Scanning library: {} found, {} indexed, {} errors",
+        progress.seen, progress.indexed, progress.errors
+    ))
+    .size(14)
+    .style(theme::Text::Color(Color::from_rgb(0.4, 0.4, 0.4)));
+
+    let progress_bar = ProgressBar::new(0.0..=progress.seen.max(1) as f32, progress.processed() as f32)
+        .height(8)
+        .width(Length::Fill);
+
+    let status_row = Row::new()
+        .push(Column::new().push(status).push(progress_bar).spacing(6).width(Length::Fill))
+        .push(Button::new(Text::new("Cancel")).on_press(Message::CancelScan))
+        .spacing(20)
+        .align_items(Alignment::Center)
+        .padding(Padding::from([10, 20]));
+
+    Container::new(status_row)
+        .width(Length::Fill)
+        .style(theme::Container::Custom(Box::new(HeaderStyle)))
+}
+
+fn create_empty_view(library: &LibrarySettings) -> Element<'static, Message> {
+    let roots = library.enabled_roots();
+    let message = if roots.is_empty() {
+        String::from("No library folders enabled. Add or enable a folder above.")
+    } else {
+        let folders = roots.iter()
+            .map(|root| root.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("No photos found in {}", folders)
+    };
+
+    let empty_text = Text::new(message)
+        .size(16)
+        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)));
+
+    Container::new(empty_text)
+        .width(iced::Length::Fill)
+        .height(iced::Length::Fill)
+        .align_x(iced::alignment::Horizontal::Center)
+        .align_y(iced::alignment::Vertical::Center)
+        .into()
+}
+
+/// Lays out only the `rows` near the viewport, with spacers standing in for the rest
+/// so the scrollbar still reflects the whole library.
+fn create_photo_grid<'a>(
+    photos: &'a [Photo],
+    layout: &GridLayout,
+    selection: &HashSet<PhotoId>,
+    thumbnails: &ThumbnailCache,
+    annotations: &Annotations,
+    edits: &EditStore,
+) -> Element<'a, Message> {
+    let columns = layout.columns();
+    let row_count = layout.row_count(photos.len());
+    let rows = layout.visible_rows(row_count);
+    let mut grid_content = Column::new().spacing(ROW_SPACING).padding(Padding::new(GRID_PADDING));
+
+    if rows.start > 0 {
+        grid_content = grid_content.push(Space::with_height(layout.rows_height(rows.start)));
+    }
+
+    for row_photos in photos.chunks(columns).take(rows.end).skip(rows.start) {
+        let mut row = Row::new().spacing(COLUMN_SPACING);
+
+        for photo in row_photos {
+            let is_selected = selection.contains(&photo.path);
+
+            let photo_card = create_photo_card(photo, is_selected, layout, thumbnails, annotations, edits);
+            row = row.push(photo_card);
+        }
+
+        row = row.push(Space::with_width(Length::Fill));
+        grid_content = grid_content.push(row);
+    }
+
+    if rows.end < row_count {
+        grid_content = grid_content.push(Space::with_height(layout.rows_height(row_count - rows.end)));
+    }
+
+    let scrollable = Scrollable::new(grid_content)
+        .id(grid_scroll_id())
+        .on_scroll(Message::GridScrolled)
+        .style(theme::Scrollable::Custom(Box::new(ScrollableStyle)));
+
+    Container::new(scrollable)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(theme::Container::Custom(Box::new(BackgroundStyle)))
+        .into()
+}
+
+fn grid_scroll_id() -> scrollable::Id {
+    scrollable::Id::new("photo-grid")
+}
+
+fn create_photo_card<'a>(
+    photo: &'a Photo,
+    is_selected: bool,
+    layout: &GridLayout,
+    thumbnails: &ThumbnailCache,
+    annotations: &Annotations,
+    edits: &EditStore,
+) -> Button<'a, Message> {
+    let image_size = layout.image_size();
+    let card_size = layout.card_size();
+    let image: Element<'a, Message> = match thumbnails.peek(photo) {
+        Some(handle) => Image::new(handle.clone())
+            .width(image_size.width)
+            .height(image_size.height)
+            .into(),
+        None => Space::new(image_size.width, image_size.height).into(),
+    };
+
+    let filename = Text::new(&photo.name)
+        .size(14)
+        .style(theme::Text::Color(Color::from_rgb(0.2, 0.2, 0.2)));
+
+    let rating = annotations.rating(&photo.path);
+    let tags = annotations.get(&photo.path)
+        .map(|annotation| annotation.tags.iter().map(String::as_str).collect::<Vec<_>>().join(", "))
+        .unwrap_or_default();
+    let edited = if edits.is_edited(&photo.path) { "✎" } else { "" };
+    let details = Text::new(format!("{} {} {}", edited, format_rating(rating), tags).trim().to_string())
+        .size(12)
+        .style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)));
+
+    let card_content = Column::new()
+        .push(image)
+        .push(filename)
+        .push(details)
+        .spacing(8)
+        .align_items(Alignment::Center)
+        .padding(Padding::new(12.0));
+
+    Button::new(card_content)
+        .width(card_size.width)
+        .height(card_size.height)
+        .padding(Padding::new(0.0))
+        .style(theme::Button::Custom(Box::new(PhotoCardStyle { is_selected })))
+        .on_press(Message::PhotoClicked(photo.path.clone()))
+}
+
+fn format_rating(rating: u8) -> String {
+    "★".repeat(rating as usize)
+}
+
+fn photo_count(count: usize) -> String {
+    if count == 1 {
+        String::from("1 photo")
+    } else {
+        format!("{} photos", count)
+    }
+}
+
+fn create_selection_toolbar(app: &PhotoOrganizer) -> Container<'_, Message> {
+    let count = app.selected_paths().len();
+
+    let mut summary = Row::new()
+        .push(Text::new(format!("{} of {} selected", count, app.filtered_photos.len())).size(14))
+        .push(Button::new(Text::new("All")).on_press(Message::SelectAll))
+        .push(Button::new(Text::new("None")).on_press(Message::SelectNone))
+        .push(Button::new(Text::new("Invert")).on_press(Message::InvertSelection))
+        .push(Space::with_width(Length::Fill))
+        .spacing(10)
+        .align_items(Alignment::Center);
+    if let Some(status) = &app.status_message {
+        summary = summary.push(
+            Text::new(status)
+                .size(14)
+                .style(theme::Text::Color(Color::from_rgb(0.4, 0.4, 0.4)))
+        );
+    }
+
+    let mut toolbar = Column::new()
+        .push(summary)
+        .spacing(10)
+        .padding(Padding::from([10, 20]));
+
+    if count > 0 {
+        let mut rating = Row::new()
+            .push(Text::new("Rate:").size(14))
+            .spacing(6)
+            .align_items(Alignment::Center);
+        for stars in 1..=MAX_RATING {
+            rating = rating.push(Button::new(Text::new(format_rating(stars))).on_press(Message::RateSelection(stars)));
+        }
+        rating = rating.push(Button::new(Text::new("Clear")).on_press(Message::RateSelection(0)));
+
+        let tagging = Row::new()
+            .push(
+                TextInput::new("Tag...", &app.tag_input)
+                    .on_input(Message::TagInput)
+                    .on_submit(Message::AddTagToSelection)
+                    .width(180)
+                    .padding(Padding::new(6.0))
+            )
+            .push(Button::new(Text::new("Add tag")).on_press(Message::AddTagToSelection))
+            .push(Button::new(Text::new("Remove tag")).on_press(Message::RemoveTagFromSelection))
+            .push(Space::with_width(20))
+            .push(rating)
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+        let mut files = Row::new()
+            .push(
+                TextInput::new("Destination folder...", &app.destination_input)
+                    .on_input(Message::DestinationInput)
+                    .padding(Padding::new(6.0))
+            )
+            .push(Button::new(Text::new("Move")).on_press(Message::MoveSelection))
+            .push(Button::new(Text::new("Export…")).on_press(Message::ExportSelection))
+            .push(
+                TextInput::new("New file name...", &app.rename_input)
+                    .on_input(Message::RenameInput)
+                    .on_submit(Message::RenameSelection)
+                    .width(180)
+                    .padding(Padding::new(6.0))
+            )
+            .push(Button::new(Text::new("Rename")).on_press_maybe((count == 1).then_some(Message::RenameSelection)))
+            .spacing(10)
+            .align_items(Alignment::Center);
+        files = if let Some(paths) = &app.pending_delete {
+            files
+                .push(Text::new(format!("Delete {}? Undo restores them until POER is closed.", photo_count(paths.len()))).size(14))
+                .push(Button::new(Text::new("Delete")).on_press(Message::ConfirmDelete))
+                .push(Button::new(Text::new("Cancel")).on_press(Message::CancelDelete))
+        } else {
+            files.push(Button::new(Text::new("Delete")).on_press(Message::DeleteSelection))
+        };
+
+        let transforms = Row::new()
+            .push(Button::new(Text::new("⟲ Rotate left")).on_press(Message::TransformSelection(Transform::RotateLeft)))
+            .push(Button::new(Text::new("⟳ Rotate right")).on_press(Message::TransformSelection(Transform::RotateRight)))
+            .push(Button::new(Text::new("⇆ Flip horizontal")).on_press(Message::TransformSelection(Transform::FlipHorizontal)))
+            .push(Button::new(Text::new("⇅ Flip vertical")).on_press(Message::TransformSelection(Transform::FlipVertical)))
+            .push(Checkbox::new(
+                "Lossless (JPEG only)",
+                app.transform_mode == TransformMode::Lossless,
+                Message::LosslessTransformsToggled,
+            ))
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+        toolbar = toolbar.push(tagging).push(transforms).push(files);
+        if !app.presets.all().is_empty() {
+            let mut presets = Row::new()
+                .push(Text::new("Apply preset:").size(14))
+                .spacing(6)
+                .align_items(Alignment::Center);
+            for preset in app.presets.all() {
+                presets = presets.push(
+                    Button::new(Text::new(preset.name.clone())).on_press(Message::ApplyPresetToSelection(preset.name.clone()))
+                );
+            }
+            toolbar = toolbar.push(presets);
+        }
+    }
+
+    Container::new(toolbar)
+        .width(Length::Fill)
+        .style(theme::Container::Custom(Box::new(HeaderStyle)))
+}
+
+/// Describes the outcome of a bulk file operation, including the first failure if any.
+fn summarize_results(action: &str, results: &[FileResult]) -> String {
+    let failures = results.iter()
+        .filter_map(|(path, result)| result.as_ref().err().map(|err| (path, err)))
+        .collect::<Vec<_>>();
+    let succeeded = results.len() - failures.len();
+
+    match failures.first() {
+        None => format!("{} {} photos.", action, succeeded),
+        Some((path, err)) => format!(
+            "{} {} photos, {} failed ({}: {}).",
+            action,
+            succeeded,
+            failures.len(),
+            path.display(),
+            err
+        ),
+    }
+}
+
+/// Format, size, destination and file names for exporting photos with their edits applied.
+fn create_export_dialog<'a>(app: &'a PhotoOrganizer, dialog: &'a ExportDialog) -> Element<'a, Message> {
+    let settings = &app.export_settings;
+    let label = |text: &str| Text::new(text.to_string()).size(14).width(120);
+    let hint = |text: String| Text::new(text).size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)));
+    let size_input = |placeholder: &str, value: &str, on_input: fn(String) -> Message| {
+        TextInput::new(placeholder, value).on_input(on_input).width(90).padding(Padding::new(6.0))
+    };
+
+    let mut quality = Row::new()
+        .push(label("Quality"))
+        .spacing(10)
+        .align_items(Alignment::Center);
+    quality = if dialog.photos.iter().any(|photo| settings.format.uses_quality(photo)) {
+        quality
+            .push(Slider::new(1..=100, settings.quality, Message::ExportQualityChanged).width(Length::Fill))
+            .push(Text::new(settings.quality.to_string()).size(14).width(30))
+    } else {
+        quality.push(hint(String::from("Lossless")))
+    };
+
+    let mut size = Row::new()
+        .push(label("Size"))
+        .push(PickList::new(&ResizeMode::ALL[..], Some(settings.resize), Message::ExportResizeSelected))
+        .spacing(10)
+        .align_items(Alignment::Center);
+    size = match settings.resize {
+        ResizeMode::FullSize => size,
+        ResizeMode::LongEdge => size
+            .push(size_input("Pixels", &dialog.long_edge, Message::ExportLongEdgeInput))
+            .push(Text::new("px").size(14)),
+        ResizeMode::Exact => size
+            .push(size_input("Width", &dialog.width, Message::ExportWidthInput))
+            .push(Text::new("×").size(14))
+            .push(size_input("Height", &dialog.height, Message::ExportHeightInput))
+            .push(Text::new("px").size(14)),
+    };
+    if settings.resize != ResizeMode::FullSize {
+        size = size
+            .push(Space::with_width(10))
+            .push(PickList::new(&ResampleFilter::ALL[..], Some(settings.filter), Message::ExportFilterSelected));
+    }
+
+    let example = dialog.photos.first().map(|path| {
+        export::file_name(settings, &app.export_job(path), 0, dialog.photos.len())
+    });
+
+    let mut form = Column::new()
+        .push(Text::new(format!("Export {}", photo_count(dialog.photos.len()))).size(20))
+        .push(
+            Row::new()
+                .push(label("Format"))
+                .push(PickList::new(&ExportFormat::ALL[..], Some(settings.format), Message::ExportFormatSelected))
+                .spacing(10)
+                .align_items(Alignment::Center)
+        )
+        .push(quality)
+        .push(size)
+        .push(
+            Row::new()
+                .push(label("Destination"))
+                .push(
+                    TextInput::new("Destination folder...", &app.destination_input)
+                        .on_input(Message::DestinationInput)
+                        .padding(Padding::new(6.0))
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+        )
+        .push(
+            Row::new()
+                .push(label("File name"))
+                .push(
+                    TextInput::new("{name}", &settings.template)
+                        .on_input(Message::ExportTemplateInput)
+                        .on_submit(Message::StartExport)
+                        .padding(Padding::new(6.0))
+                )
+                .spacing(10)
+                .align_items(Alignment::Center)
+        )
+        .push(hint(String::from(TEMPLATE_HELP)));
+    if let Some(example) = example {
+        form = form.push(hint(format!("Example: {}", example)));
+    }
+    if let Some(error) = &dialog.error {
+        form = form.push(Text::new(error).size(14).style(theme::Text::Color(Color::from_rgb(0.8, 0.2, 0.2))));
+    }
+    form = form.push(
+        Row::new()
+            .push(Space::with_width(Length::Fill))
+            .push(Button::new(Text::new("Cancel")).on_press(Message::CloseExportDialog))
+            .push(Button::new(Text::new("Export")).on_press(Message::StartExport))
+            .spacing(10)
+    );
+
+    Container::new(form.spacing(14).padding(Padding::new(20.0)).max_width(640))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .style(theme::Container::Custom(Box::new(BackgroundStyle)))
+        .into()
+}
+
+fn create_export_status(run: &ExportRun) -> Container<'static, Message> {
+    let total = run.jobs.len();
+    let failed = run.results.iter().filter(|(_, result)| result.is_err()).count();
+    let status = Text::new(format!("Exporting: {} of {} done, {} failed", run.results.len(), total, failed))
+        .size(14)
+        .style(theme::Text::Color(Color::from_rgb(0.4, 0.4, 0.4)));
+
+    let progress_bar = ProgressBar::new(0.0..=total.max(1) as f32, run.results.len() as f32)
+        .height(8)
+        .width(Length::Fill);
+
+    let status_row = Row::new()
+        .push(Column::new().push(status).push(progress_bar).spacing(6).width(Length::Fill))
+        .push(Button::new(Text::new("Cancel")).on_press(Message::CancelExport))
+        .spacing(20)
+        .align_items(Alignment::Center)
+        .padding(Padding::from([10, 20]));
+
+    Container::new(status_row)
+        .width(Length::Fill)
+        .style(theme::Container::Custom(Box::new(HeaderStyle)))
+}
+
+/// Every recorded step, newest last. Clicking a step undoes or redoes everything after or up to it.
+fn create_history_panel(history: &History) -> Container<'_, Message> {
+    let position = history.position();
+    let entry = |index: usize, label: &str| {
+        let color = match index.cmp(&position) {
+            Ordering::Less => Color::from_rgb(0.3, 0.3, 0.3),
+            Ordering::Equal => Color::from_rgb(0.2, 0.5, 0.9),
+            Ordering::Greater => Color::from_rgb(0.65, 0.65, 0.65),
+        };
+        Button::new(Text::new(label.to_string()).size(14).style(theme::Text::Color(color)))
+            .style(theme::Button::Text)
+            .padding(Padding::from([4, 0]))
+            .on_press(Message::JumpToHistory(index))
+    };
+
+    let mut steps = Column::new()
+        .push(Text::new("History").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .push(entry(0, "Start of session"))
+        .spacing(2)
+        .padding(Padding::new(20.0));
+    for (index, step) in history.steps().iter().enumerate() {
+        steps = steps.push(entry(index + 1, &step.label));
+    }
+
+    Container::new(Scrollable::new(steps).style(theme::Scrollable::Custom(Box::new(ScrollableStyle))))
+        .width(HISTORY_PANEL_WIDTH)
+        .height(Length::Fill)
+        .style(theme::Container::Custom(Box::new(HeaderStyle)))
+}
+
+fn create_viewer<'a>(app: &'a PhotoOrganizer, viewer: &'a ViewerState) -> Element<'a, Message> {
+    let photo = app.photo_index.get(&viewer.photo).map(|&index| &app.photos[index]);
+
+    let zoom_label = match viewer.zoom {
+        Zoom::Fit => String::from("Fit"),
+        Zoom::Scale(scale) => format!("{:.0}%", scale * 100.0),
+    };
+
+    let title = Text::new(photo.map(|photo| photo.name.as_str()).unwrap_or_default())
+        .size(16)
+        .style(theme::Text::Color(Color::from_rgb(0.2, 0.2, 0.2)));
+
+    let toolbar = match &viewer.crop {
+        Some(crop) => create_crop_toolbar(crop).push(title),
+        None => Row::new()
+            .push(Button::new(Text::new("← Back to grid")).on_press(Message::CloseViewer))
+            .push(title)
+            .push(Space::with_width(Length::Fill))
+            .push(Button::new(Text::new("Crop")).on_press(Message::StartCrop))
+            .push(Button::new(Text::new("⟲")).on_press(Message::TransformViewed(Transform::RotateLeft)))
+            .push(Button::new(Text::new("⟳")).on_press(Message::TransformViewed(Transform::RotateRight)))
+            .push(Button::new(Text::new("⇆")).on_press(Message::TransformViewed(Transform::FlipHorizontal)))
+            .push(Button::new(Text::new("⇅")).on_press(Message::TransformViewed(Transform::FlipVertical)))
+            .push(Button::new(Text::new("Export…")).on_press(Message::ExportViewed))
+            .push(Space::with_width(20))
+            .push(Text::new(zoom_label).size(14))
+            .push(Button::new(Text::new("Fit")).on_press(Message::ViewerZoomChanged(Zoom::Fit, Vector::new(0.0, 0.0))))
+            .push(Button::new(Text::new("100%")).on_press(Message::ViewerZoomChanged(Zoom::Scale(1.0), Vector::new(0.0, 0.0)))),
+    }
+    .spacing(12)
+    .align_items(Alignment::Center)
+    .padding(Padding::from([10, 20]));
+
+    let image: Element<'a, Message> = match (&viewer.preview, &viewer.error) {
+        (Some(preview), _) if viewer.crop.is_some() => {
+            let ratio = viewer.crop.as_ref().and_then(|crop| crop.ratio_value(preview.size));
+            let rect = viewer.crop.as_ref().map_or(FULL_CROP, |crop| crop.rect);
+            CropView::new(preview.handle.clone(), preview.size, rect, ratio, Message::CropChanged).into()
+        }
+        (Some(preview), _) => {
+            // Zoom levels are relative to the original; once zoomed in past the proxy,
+            // the full resolution rendering takes over when it is up to date.
+            let handle = viewer.full_view.as_ref()
+                .filter(|view| viewer.shows_full_resolution() && view.edits == app.preview_edits(&viewer.photo))
+                .map_or(&preview.handle, |view| &view.handle);
+            let size = Size::new(preview.size.width * viewer.proxy_scale, preview.size.height * viewer.proxy_scale);
+            let mut view = PhotoView::new(
+                handle.clone(),
+                size,
+                viewer.zoom,
+                viewer.offset,
+                Message::ViewerZoomChanged,
+                Message::ViewerPanned,
+            )
+            .overlays(
+                [
+                    preview.shadow_clipping.as_ref().filter(|_| app.show_shadow_clipping),
+                    preview.highlight_clipping.as_ref().filter(|_| app.show_highlight_clipping),
+                ]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect()
+            );
+            if let Some(loupe) = &viewer.loupe {
+                let region = loupe.patch.as_ref().map(|patch| {
+                    let CropRect { x, y, width, height } = patch.region;
+                    Rectangle { x, y, width, height }
+                });
+                view = view.marker(region).on_pick(Message::LoupeMoved);
+            }
+            view.into()
+        }
+        (None, Some(err)) => Container::new(
+            Text::new(format!("Could not show photo: {}", err))
+                .size(16)
+                .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)))
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into(),
+        (None, None) => Container::new(
+            Text::new("Loading photo...")
+                .size(16)
+                .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)))
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into(),
+    };
+
+    let mut body = Row::new().push(image).height(Length::Fill);
+    if let Some(photo) = photo {
+        let mut tools = Column::new().spacing(20);
+        if let Some(preview) = &viewer.preview {
+            tools = tools.push(create_histogram_panel(app, &preview.histogram));
+        }
+        if viewer.crop.is_none() {
+            tools = tools.push(create_edit_tools(app, viewer, &photo.path));
+        }
+        body = body.push(create_photo_info(photo, tools, app.edits.get(&photo.path)));
+    }
+
+    let mut content = Column::new()
+        .push(Container::new(toolbar).width(Length::Fill).style(theme::Container::Custom(Box::new(HeaderStyle))));
+    // Edits that fail to render leave the last good preview in place.
+    if let (Some(_), Some(err)) = (&viewer.preview, &viewer.error) {
+        content = content.push(
+            Container::new(
+                Text::new(format!("Could not render edits: {}", err))
+                    .size(14)
+                    .style(theme::Text::Color(Color::from_rgb(0.8, 0.2, 0.2)))
+            )
+            .padding(Padding::from([6, 20]))
+        );
+    }
+    Container::new(content.push(body))
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .style(theme::Container::Custom(Box::new(BackgroundStyle)))
+    .into()
+}
+
+fn create_photo_info<'a>(
+    photo: &'a Photo,
+    tools: Column<'a, Message>,
+    edits: &'a [EditOp],
+) -> Container<'a, Message> {
+    let exif = &photo.exif;
+    let mut details = vec![
+        (String::from("Dimensions"), format!("{} × {}", photo.width, photo.height)),
+        (String::from("File size"), format_file_size(photo.size)),
+    ];
+    if let Some(captured_at) = exif.captured_at {
+        details.push((String::from("Taken"), captured_at.to_string()));
+    }
+    let camera = [exif.camera_make.as_deref(), exif.camera_model.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !camera.is_empty() {
+        details.push((String::from("Camera"), camera));
+    }
+    if let Some(lens) = &exif.lens {
+        details.push((String::from("Lens"), lens.clone()));
+    }
+    let exposure = exif.exposure_summary();
+    if !exposure.is_empty() {
+        details.push((String::from("Exposure"), exposure));
+    }
+    if let Some(gps) = exif.gps {
+        details.push((String::from("Location"), format!("{:.5}, {:.5}", gps.latitude, gps.longitude)));
+    }
+
+    let mut info = Column::new().spacing(12).padding(Padding::new(20.0));
+    info = info.push(tools);
+    for (label, value) in details {
+        info = info.push(
+            Column::new()
+                .push(Text::new(label).size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+                .push(Text::new(value).size(14))
+                .spacing(2)
+        );
+    }
+    info = info.push(create_edit_stack(edits));
+
+    Container::new(Scrollable::new(info).style(theme::Scrollable::Custom(Box::new(ScrollableStyle))))
+        .width(260)
+        .height(Length::Fill)
+        .style(theme::Container::Custom(Box::new(HeaderStyle)))
+}
+
+/// Aspect ratio, straighten and confirm controls shown while the crop tool is open.
+fn create_crop_toolbar(crop: &CropSession) -> Row<'_, Message> {
+    let mut toolbar = Row::new()
+        .push(Button::new(Text::new("Cancel")).on_press(Message::CancelCrop))
+        .push(Button::new(Text::new("Apply crop")).on_press(Message::ApplyCrop))
+        .push(Button::new(Text::new("Reset")).on_press(Message::ResetCrop))
+        .push(Text::new("Ratio:").size(14))
+        .push(PickList::new(&AspectRatio::ALL[..], Some(crop.ratio), Message::CropRatioSelected));
+
+    if crop.ratio == AspectRatio::Custom {
+        toolbar = toolbar
+            .push(TextInput::new("W", &crop.custom_width).on_input(Message::CropCustomWidth).width(50).padding(Padding::new(6.0)))
+            .push(Text::new(":").size(14))
+            .push(TextInput::new("H", &crop.custom_height).on_input(Message::CropCustomHeight).width(50).padding(Padding::new(6.0)));
+    }
+
+    toolbar
+        .push(Text::new(format!("Straighten: {:+.1}°", crop.straighten)).size(14))
+        .push(
+            Slider::new(-MAX_STRAIGHTEN..=MAX_STRAIGHTEN, crop.straighten, Message::StraightenChanged)
+                .on_release(Message::StraightenReleased)
+                .step(0.1)
+                .width(200)
+        )
+        .push(Space::with_width(Length::Fill))
+}
+
+/// Histogram of the viewed photo as edited, with toggles for the clipping overlays.
+fn create_histogram_panel<'a>(app: &PhotoOrganizer, histogram: &'a Histogram) -> Column<'a, Message> {
+    let indicator = |clipping: Clipping, shown: bool| {
+        let clipped = histogram.clipped(clipping);
+        let label = match clipping {
+            Clipping::Shadows => "Shadows",
+            Clipping::Highlights => "Highlights",
+        };
+        let color = match (clipping, clipped > 0.0) {
+            (_, false) => Color::from_rgb(0.5, 0.5, 0.5),
+            (Clipping::Shadows, true) => Color::from_rgb(0.15, 0.4, 0.95),
+            (Clipping::Highlights, true) => Color::from_rgb(0.9, 0.15, 0.15),
+        };
+        let text = Text::new(format!("{} {}: {:.1}%", if shown { "●" } else { "○" }, label, clipped * 100.0))
+            .size(12)
+            .style(theme::Text::Color(color));
+        Button::new(text)
+            .style(theme::Button::Text)
+            .padding(0)
+            .on_press(Message::ToggleClippingOverlay(clipping))
+    };
+
+    Column::new()
+        .push(Text::new("Histogram").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .push(HistogramView::new(histogram))
+        .push(
+            Row::new()
+                .push(indicator(Clipping::Shadows, app.show_shadow_clipping))
+                .push(Space::with_width(Length::Fill))
+                .push(indicator(Clipping::Highlights, app.show_highlight_clipping))
+        )
+        .spacing(6)
+}
+
+/// Levels, tone curve, adjustment, detail, LUT and preset controls for the viewed photo.
+fn create_edit_tools<'a>(app: &'a PhotoOrganizer, viewer: &'a ViewerState, id: &Path) -> Column<'a, Message> {
+    let levels = match viewer.levels {
+        Some(levels) => create_levels_dialog(levels),
+        None => Column::new().push(Button::new(Text::new("Levels…")).on_press(Message::OpenLevels)),
+    };
+    let histogram = viewer.preview.as_ref().map(|preview| preview.histogram.channel(viewer.curve_channel));
+
+    Column::new()
+        .push(levels)
+        .push(create_tone_curve_panel(&app.edits.tone_curve(id), viewer.curve_channel, histogram))
+        .push(create_adjustments_panel(app.edits.adjustments(id)))
+        .push(create_detail_panel(app.edits.sharpening(id), app.edits.noise_reduction(id), viewer.loupe.as_ref()))
+        .push(create_lut_panel(app, app.edits.lut(id)))
+        .push(create_presets_panel(app, viewer))
+        .spacing(20)
+}
+
+/// Black point, gamma and white point sliders, previewed until applied.
+fn create_levels_dialog(levels: Levels) -> Column<'static, Message> {
+    let slider_row = |label: &str, value: String| {
+        Row::new()
+            .push(Text::new(label.to_string()).size(14).width(Length::Fill))
+            .push(Text::new(value).size(14))
+    };
+
+    Column::new()
+        .push(Text::new("Levels").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .push(slider_row("Black point", format!("{:.0}", levels.black * 255.0)))
+        .push(
+            Slider::new(0.0..=255.0, levels.black * 255.0, move |black| Message::LevelsChanged(levels.with_black(black / 255.0)))
+                .step(1.0)
+        )
+        .push(slider_row("Gamma", format!("{:.2}", levels.gamma)))
+        .push(
+            Slider::new(0.1..=5.0, levels.gamma, move |gamma| Message::LevelsChanged(Levels { gamma, ..levels }))
+                .step(0.01)
+        )
+        .push(slider_row("White point", format!("{:.0}", levels.white * 255.0)))
+        .push(
+            Slider::new(0.0..=255.0, levels.white * 255.0, move |white| Message::LevelsChanged(levels.with_white(white / 255.0)))
+                .step(1.0)
+        )
+        .push(
+            Row::new()
+                .push(Button::new(Text::new("Cancel")).on_press(Message::CancelLevels))
+                .push(Button::new(Text::new("Apply")).on_press(Message::ApplyLevels))
+                .spacing(8)
+        )
+        .spacing(6)
+}
+
+/// Curve editor for one channel of the tone curve, drawn over that channel's histogram.
+fn create_tone_curve_panel<'a>(
+    tone_curve: &ToneCurve,
+    channel: CurveChannel,
+    histogram: Option<&'a [u32; 256]>,
+) -> Column<'a, Message> {
+    let color = match channel {
+        CurveChannel::Rgb => Color::from_rgb(0.9, 0.9, 0.9),
+        CurveChannel::Red => Color::from_rgb(0.9, 0.3, 0.3),
+        CurveChannel::Green => Color::from_rgb(0.3, 0.8, 0.3),
+        CurveChannel::Blue => Color::from_rgb(0.35, 0.5, 0.95),
+    };
+    let curve = tone_curve.curve(channel);
+
+    let mut header = Row::new()
+        .push(Text::new("Tone curve").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))).width(Length::Fill))
+        .push(PickList::new(&CurveChannel::ALL[..], Some(channel), Message::CurveChannelSelected).text_size(14))
+        .spacing(8)
+        .align_items(Alignment::Center);
+    if !curve.is_identity() {
+        header = header.push(Button::new(Text::new("Reset").size(14)).on_press(Message::ResetCurve));
+    }
+
+    Column::new()
+        .push(header)
+        .push(CurveEditor::new(curve, histogram, color, Message::CurveChanged, Message::CurveReleased))
+        .push(
+            Text::new("Click to add a point, right-click to remove one.")
+                .size(12)
+                .style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)))
+        )
+        .spacing(6)
+}
+
+/// Sharpening and noise reduction sliders, with a loupe showing their effect at 100%.
+fn create_detail_panel(sharpening: Sharpening, noise_reduction: NoiseReduction, loupe: Option<&Loupe>) -> Column<'static, Message> {
+    let slider_row = |label: &str, value: String| {
+        Row::new()
+            .push(Text::new(label.to_string()).size(14).width(Length::Fill))
+            .push(Text::new(value).size(14))
+    };
+    let hint = |text: &str| Text::new(text.to_string()).size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)));
+
+    let mut panel = Column::new()
+        .push(
+            Row::new()
+                .push(Text::new("Detail").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))).width(Length::Fill))
+                .push(Button::new(Text::new(if loupe.is_some() { "Hide loupe" } else { "Loupe" }).size(14)).on_press(Message::ToggleLoupe))
+                .align_items(Alignment::Center)
+        )
+        .spacing(6);
+    match loupe {
+        Some(loupe) => {
+            panel = match (&loupe.error, &loupe.image) {
+                (Some(err), _) => panel.push(
+                    Text::new(format!("Could not render the loupe: {}", err))
+                        .size(12)
+                        .style(theme::Text::Color(Color::from_rgb(0.8, 0.2, 0.2)))
+                ),
+                (None, Some(image)) => panel.push(Image::new(image.clone())),
+                (None, None) => panel.push(hint("Rendering at 100%…")),
+            };
+            panel = panel.push(hint("Click the photo to move the loupe."));
+        }
+        None => panel = panel.push(hint("Sharpening and noise reduction only show in the loupe.")),
+    }
+
+    panel = panel
+        .push(slider_row("Sharpening", format!("{:.0}%", sharpening.amount * 100.0)))
+        .push(
+            Slider::new(0.0..=3.0, sharpening.amount, move |amount| Message::SharpeningChanged(Sharpening { amount, ..sharpening }))
+                .on_release(Message::DetailReleased)
+                .step(0.05)
+        )
+        .push(slider_row("Radius", format!("{:.1} px", sharpening.radius)))
+        .push(
+            Slider::new(0.3..=5.0, sharpening.radius, move |radius| Message::SharpeningChanged(Sharpening { radius, ..sharpening }))
+                .on_release(Message::DetailReleased)
+                .step(0.1)
+        )
+        .push(slider_row("Threshold", format!("{:.0}", sharpening.threshold)))
+        .push(
+            Slider::new(0.0..=50.0, sharpening.threshold, move |threshold| Message::SharpeningChanged(Sharpening { threshold, ..sharpening }))
+                .on_release(Message::DetailReleased)
+                .step(1.0)
+        )
+        .push(slider_row("Luminance noise", format!("{:.0}%", noise_reduction.luminance * 100.0)))
+        .push(
+            Slider::new(0.0..=1.0, noise_reduction.luminance, move |luminance| {
+                Message::NoiseReductionChanged(NoiseReduction { luminance, ..noise_reduction })
+            })
+            .on_release(Message::DetailReleased)
+            .step(0.01)
+        )
+        .push(slider_row("Colour noise", format!("{:.0}%", noise_reduction.chroma * 100.0)))
+        .push(
+            Slider::new(0.0..=1.0, noise_reduction.chroma, move |chroma| {
+                Message::NoiseReductionChanged(NoiseReduction { chroma, ..noise_reduction })
+            })
+            .on_release(Message::DetailReleased)
+            .step(0.01)
+        );
+    if !sharpening.is_identity() || !noise_reduction.is_identity() {
+        panel = panel.push(Button::new(Text::new("Reset detail")).on_press(Message::ResetDetail));
+    }
+    panel
+}
+
+/// The LUT applied to the viewed photo with its intensity, and importing new `.cube` files.
+fn create_lut_panel(app: &PhotoOrganizer, lut: Option<(String, f32)>) -> Column<'_, Message> {
+    let mut panel = Column::new()
+        .push(Text::new("LUT").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .spacing(6);
+    if !app.luts.is_empty() {
+        panel = panel.push(
+            PickList::new(&app.luts[..], lut.as_ref().map(|(name, _)| name.clone()), Message::LutSelected)
+                .placeholder("Choose a LUT...")
+                .text_size(14)
+                .width(Length::Fill)
+        );
+    }
+    if let Some((_, intensity)) = lut {
+        panel = panel
+            .push(
+                Row::new()
+                    .push(Text::new("Intensity").size(14).width(Length::Fill))
+                    .push(Text::new(format!("{:.0}%", intensity * 100.0)).size(14))
+            )
+            .push(
+                Slider::new(0.0..=1.0, intensity, Message::LutIntensityChanged)
+                    .on_release(Message::LutReleased)
+                    .step(0.01)
+            )
+            .push(Button::new(Text::new("Remove LUT")).on_press(Message::RemoveLut));
+    }
+    panel = panel.push(
+        Row::new()
+            .push(
+                TextInput::new(".cube file...", &app.lut_input)
+                    .on_input(Message::LutInput)
+                    .on_submit(Message::ImportLut)
+                    .padding(Padding::new(6.0))
+            )
+            .push(Button::new(Text::new("Import")).on_press_maybe((!app.lut_input.trim().is_empty()).then_some(Message::ImportLut)))
+            .spacing(6)
+            .align_items(Alignment::Center)
+    );
+    if let Some(error) = &app.lut_error {
+        panel = panel.push(Text::new(error).size(12).style(theme::Text::Color(Color::from_rgb(0.8, 0.2, 0.2))));
+    }
+    panel
+}
+
+/// Saved presets, each shown on the viewed photo; clicking one applies it.
+fn create_presets_panel<'a>(app: &'a PhotoOrganizer, viewer: &'a ViewerState) -> Column<'a, Message> {
+    let mut panel = Column::new()
+        .push(Text::new("Presets").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .spacing(6);
+
+    for row in app.presets.all().chunks(2) {
+        let mut cells = Row::new().spacing(8);
+        for preset in row {
+            let thumbnail = viewer.preset_thumbnails.iter()
+                .find(|(name, _)| *name == preset.name)
+                .map(|(_, handle)| handle.clone());
+            let mut card = Column::new().spacing(4).align_items(Alignment::Center);
+            if let Some(thumbnail) = thumbnail {
+                card = card.push(Image::new(thumbnail).width(Length::Fill));
+            }
+            card = card.push(Text::new(preset.name.clone()).size(12));
+            cells = cells.push(
+                Column::new()
+                    .push(
+                        Button::new(card)
+                            .style(theme::Button::Text)
+                            .padding(0)
+                            .on_press(Message::ApplyPreset(preset.name.clone()))
+                    )
+                    .push(
+                        Button::new(Text::new("Delete").size(12))
+                            .style(theme::Button::Text)
+                            .padding(0)
+                            .on_press(Message::DeletePreset(preset.name.clone()))
+                    )
+                    .align_items(Alignment::Center)
+                    .width(Length::FillPortion(1))
+            );
+        }
+        if row.len() < 2 {
+            cells = cells.push(Space::with_width(Length::FillPortion(1)));
+        }
+        panel = panel.push(cells);
+    }
+
+    let can_save = !app.preset_name_input.trim().is_empty()
+        && app.edits.get(&viewer.photo).iter().any(|op| !op.is_framing());
+    panel.push(
+        Row::new()
+            .push(
+                TextInput::new("Preset name...", &app.preset_name_input)
+                    .on_input(Message::PresetNameInput)
+                    .on_submit(Message::SavePreset)
+                    .padding(Padding::new(6.0))
+            )
+            .push(Button::new(Text::new("Save")).on_press_maybe(can_save.then_some(Message::SavePreset)))
+            .spacing(6)
+            .align_items(Alignment::Center)
+    )
+}
+
+/// Sliders for the tone and colour adjustments of the viewed photo.
+fn create_adjustments_panel(adjustments: Adjustments) -> Column<'static, Message> {
+    let mut panel = Column::new()
+        .push(Text::new("Adjustments").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .spacing(6);
+
+    for adjustment in Adjustment::ALL {
+        let value = adjustment.get(&adjustments);
+        panel = panel
+            .push(
+                Row::new()
+                    .push(Text::new(adjustment.label()).size(14).width(Length::Fill))
+                    .push(Text::new(adjustment.format(value)).size(14))
+            )
+            .push(
+                Slider::new(adjustment.range(), value, move |value| Message::AdjustmentChanged(adjustment, value))
+                    .on_release(Message::AdjustmentReleased)
+                    .step(adjustment.step())
+            );
+    }
+    if !adjustments.is_identity() {
+        panel = panel.push(Button::new(Text::new("Reset adjustments")).on_press(Message::ResetAdjustments));
+    }
+    panel
+}
+
+/// Lists the edit operations of the viewed photo, each removable on its own.
+fn create_edit_stack(edits: &[EditOp]) -> Column<'static, Message> {
+    let mut stack = Column::new()
+        .push(Text::new("Edits").size(12).style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5))))
+        .spacing(6);
+
+    if edits.is_empty() {
+        return stack.push(Text::new("Original").size(14));
+    }
+    for (index, op) in edits.iter().enumerate() {
+        stack = stack.push(
+            Row::new()
+                .push(Text::new(op.label()).size(14).width(Length::Fill))
+                .push(Button::new(Text::new("×").size(14)).on_press(Message::RemoveEdit(index)))
+                .spacing(8)
+                .align_items(Alignment::Center)
+        );
+    }
+    stack.push(Button::new(Text::new("Revert to original")).on_press(Message::RevertEdits))
+}
+
+fn format_file_size(bytes: u64) -> String {
+    match bytes {
+        0..=1_023 => format!("{} B", bytes),
+        1_024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1_024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+fn create_sorting_controls(app: &PhotoOrganizer) -> Container<'static, Message> {
+    let sort_criteria = Row::new()
+        .push(Text::new("Sort by:").size(14))
+        .push(
+            Radio::new("Name", SortCriteria::Name, Some(app.sort_criteria), Message::SortCriteriaChanged)
+        )
+        .push(
+            Radio::new("Date taken", SortCriteria::Date, Some(app.sort_criteria), Message::SortCriteriaChanged)
+        )
+        .push(
+            Radio::new("Size", SortCriteria::Size, Some(app.sort_criteria), Message::SortCriteriaChanged)
+        );
+
+    let sort_order = Button::new(
+        Text::new(match app.sort_order {
+            SortOrder::Ascending => "↑ Ascending",
+            SortOrder::Descending => "↓ Descending",
+        })
+    )
+    .on_press(Message::ToggleSortOrder);
+
+    let thumbnail_size = Row::new()
+        .push(Text::new("Thumbnail size:").size(14))
+        .push(
+            Slider::new(THUMBNAIL_SIZES, app.grid.thumbnail_size, Message::ThumbnailSizeChanged)
+                .step(10.0)
+                .width(200)
+        )
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+    Container::new(
+        Column::new()
+            .push(sort_criteria)
+            .push(Row::new().push(sort_order).push(thumbnail_size).spacing(30).align_items(Alignment::Center))
+            .spacing(15)
+    )
+    .width(Length::Fill)
+    .style(theme::Container::Custom(Box::new(BackgroundStyle)))
+}
+
+fn create_library_controls(app: &PhotoOrganizer) -> Container<'_, Message> {
+    let mut roots = Column::new()
+        .push(Text::new("Library folders:").size(14))
+        .spacing(8);
+
+    for (index, root) in app.library.roots.iter().enumerate() {
+        let depth = match app.depth_inputs.get(&root.path) {
+            Some(depth) => depth.clone(),
+            None => root.max_depth.map(|depth| depth.to_string()).unwrap_or_default(),
+        };
+
+        roots = roots.push(
+            Row::new()
+                .push(
+                    Checkbox::new(root.path.display().to_string(), root.enabled, move |_| Message::ToggleLibraryRoot(index))
+                        .width(Length::Fill)
+                )
+                .push(Text::new("Depth:").size(14))
+                .push(
+                    TextInput::new("Unlimited", &depth)
+                        .on_input(move |value| Message::LibraryRootDepthChanged(index, value))
+                        .on_submit(Message::LibraryRootDepthSubmitted(index))
+                        .width(90)
+                        .padding(Padding::new(4.0))
+                )
+                .push(Button::new(Text::new("Remove")).on_press(Message::RemoveLibraryRoot(index)))
+                .spacing(10)
+                .align_items(Alignment::Center)
+        );
+    }
+
+    let add_root = Row::new()
+        .push(
+            TextInput::new("Add a folder path...", &app.new_root_input)
+                .on_input(Message::NewRootInput)
+                .on_submit(Message::AddLibraryRoot)
+                .padding(Padding::new(8.0))
+        )
+        .push(Button::new(Text::new("Add folder")).on_press(Message::AddLibraryRoot))
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+    Container::new(roots.push(add_root))
+        .width(Length::Fill)
+        .style(theme::Container::Custom(Box::new(BackgroundStyle)))
+}
+
+impl PhotoOrganizer {
+    /// The photo grid with its filters and toolbars.
+    fn view_library(&self) -> Element<'_, Message> {
+        let filters = create_filters(self);
+
+        let content = if self.filtered_photos.is_empty() {
+            match &self.scan {
+                Some(scan) => create_loading_view(&scan.progress),
+                None => create_empty_view(&self.library),
+            }
+        } else {
+            create_photo_grid(
+                &self.filtered_photos,
+                &self.grid,
+                &self.selection,
+                &self.thumbnails,
+                &self.annotations,
+                &self.edits,
+            )
+        };
+
+        let mut layout = Column::new().push(filters);
+
+        if let Some(scan) = &self.scan {
+            layout = layout.push(create_scan_status(&scan.progress));
+        }
+        if !self.filtered_photos.is_empty() {
+            layout = layout.push(create_selection_toolbar(self));
+        }
+
+        layout
+            .push(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn grid_shortcut(&mut self, shortcut: Shortcut) -> Command<Message> {
+        let count = self.filtered_photos.len();
+        if count == 0 {
+            return Command::none();
+        }
+        let last = count - 1;
+        let columns = self.grid.columns();
+        let page = columns * PAGE_ROWS;
+
+        let Some(current) = self.focused_index() else {
+            return match shortcut {
+                Shortcut::Close => {
+                    self.selection.clear();
+                    Command::none()
+                }
+                Shortcut::SelectAll => self.update(Message::SelectAll),
+                Shortcut::Open => Command::none(),
+                Shortcut::End => self.select_in_grid(last),
+                _ => self.select_in_grid(0),
+            };
+        };
+
+        match shortcut {
+            Shortcut::Up => self.select_in_grid(current.saturating_sub(columns)),
+            Shortcut::Down => {
+                // Only move down when there is a row below, landing on its last card if it is short.
+                if current / columns < last / columns {
+                    self.select_in_grid((current + columns).min(last))
+                } else {
+                    Command::none()
+                }
+            }
+            Shortcut::Left => self.select_in_grid(current.saturating_sub(1)),
+            Shortcut::Right => self.select_in_grid((current + 1).min(last)),
+            Shortcut::Home => self.select_in_grid(0),
+            Shortcut::End => self.select_in_grid(last),
+            Shortcut::PageUp => self.select_in_grid(current.saturating_sub(page)),
+            Shortcut::PageDown => self.select_in_grid((current + page).min(last)),
+            Shortcut::Open => self.open_viewer(self.filtered_photos[current].path.clone()),
+            Shortcut::Close => {
+                self.selected_photo = None;
+                self.selection.clear();
+                Command::none()
+            }
+            Shortcut::SelectAll => self.update(Message::SelectAll),
+            Shortcut::Undo | Shortcut::Redo => Command::none(),
+        }
+    }
+
+    fn viewer_shortcut(&mut self, shortcut: Shortcut) -> Command<Message> {
+        // While cropping, Escape leaves the crop tool and navigation is disabled.
+        if self.viewer.as_ref().is_some_and(|viewer| viewer.crop.is_some()) {
+            return if shortcut == Shortcut::Close {
+                self.cancel_crop()
+            } else {
+                Command::none()
+            };
+        }
+        if shortcut == Shortcut::Close && self.viewer.as_ref().is_some_and(|viewer| viewer.levels.is_some()) {
+            return self.cancel_levels();
+        }
+        let Some(current) = self.viewer.as_ref().and_then(|viewer| self.filtered_index.get(&viewer.photo).copied()) else {
+            // The viewed photo was filtered out, so there is nothing to navigate relative to.
+            if shortcut == Shortcut::Close {
+                self.viewer = None;
+            }
+            return Command::none();
+        };
+        let last = self.filtered_photos.len().saturating_sub(1);
+
+        let target = match shortcut {
+            Shortcut::Left | Shortcut::PageUp => current.saturating_sub(1),
+            Shortcut::Right | Shortcut::PageDown => (current + 1).min(last),
+            Shortcut::Home => 0,
+            Shortcut::End => last,
+            Shortcut::Close => {
+                self.viewer = None;
+                return self.scroll_to_selection();
+            }
+            Shortcut::Up | Shortcut::Down | Shortcut::Open | Shortcut::SelectAll | Shortcut::Undo | Shortcut::Redo => {
+                return Command::none();
+            }
+        };
+
+        if target == current {
+            return Command::none();
+        }
+        let id = self.filtered_photos[target].path.clone();
+        self.select_only(id.clone());
+        self.open_viewer(id)
+    }
+
+    fn select_in_grid(&mut self, index: usize) -> Command<Message> {
+        let columns = self.grid.columns();
+        let previous_row = self.focused_index().map(|index| index / columns);
+        self.select_only(self.filtered_photos[index].path.clone());
+
+        if previous_row == Some(index / columns) {
+            Command::none()
+        } else {
+            self.scroll_to_selection()
+        }
+    }
+
+    /// Makes `id` the only selected photo and the anchor for range selection.
+    fn select_only(&mut self, id: PhotoId) {
+        self.selection = HashSet::from([id.clone()]);
+        self.selected_photo = Some(id);
+    }
+
+    /// Position of the focused photo in the grid, if it passes the current filters.
+    fn focused_index(&self) -> Option<usize> {
+        self.selected_photo.as_ref().and_then(|id| self.filtered_index.get(id).copied())
+    }
+
+    /// Selected photos that pass the current filters, in grid order; bulk actions only touch these.
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        self.filtered_photos.iter()
+            .filter(|photo| self.selection.contains(&photo.path))
+            .map(|photo| photo.path.clone())
+            .collect()
+    }
+
+    fn open_export_dialog(&mut self, photos: Vec<PhotoId>) {
+        if photos.is_empty() {
+            return;
+        }
+        let settings = &self.export_settings;
+        self.export_dialog = Some(ExportDialog {
+            photos,
+            long_edge: settings.long_edge.to_string(),
+            width: settings.width.to_string(),
+            height: settings.height.to_string(),
+            error: None,
+        });
+    }
+
+    /// Checks the export dialog and starts exporting its photos in the background.
+    fn start_export(&mut self) {
+        let Some(dialog) = &mut self.export_dialog else {
+            return;
+        };
+        let size = |value: &str| value.trim().parse::<u32>().ok().filter(|&size| size > 0);
+        let (long_edge, width, height) = (size(&dialog.long_edge), size(&dialog.width), size(&dialog.height));
+        let sizes_valid = match self.export_settings.resize {
+            ResizeMode::FullSize => true,
+            ResizeMode::LongEdge => long_edge.is_some(),
+            ResizeMode::Exact => width.is_some() && height.is_some(),
+        };
+        if !sizes_valid {
+            dialog.error = Some(String::from("Enter a size in pixels."));
+            return;
+        }
+        if self.destination_input.trim().is_empty() {
+            dialog.error = Some(String::from("Enter a destination folder."));
+            return;
+        }
+        if self.export.is_some() {
+            dialog.error = Some(String::from("Wait for the running export to finish."));
+            return;
+        }
+
+        let settings = &mut self.export_settings;
+        settings.long_edge = long_edge.unwrap_or(settings.long_edge);
+        settings.width = width.unwrap_or(settings.width);
+        settings.height = height.unwrap_or(settings.height);
+        settings.destination = PathBuf::from(self.destination_input.trim());
+        let Some(dialog) = self.export_dialog.take() else {
+            return;
+        };
+        let jobs = dialog.photos.iter().map(|path| self.export_job(path)).collect();
+        self.export_count += 1;
+        self.export = Some(ExportRun {
+            id: self.export_count,
+            jobs: Arc::new(jobs),
+            settings: Arc::new(self.export_settings.clone()),
+            results: Vec::new(),
+        });
+    }
+
+    fn export_job(&self, path: &Path) -> ExportJob {
+        let exif = self.photo_index.get(path).map(|&index| &self.photos[index].exif);
+        ExportJob {
+            path: path.to_path_buf(),
+            orientation: exif.and_then(|exif| exif.orientation),
+            edits: self.edits.get(path).to_vec(),
+            captured_at: exif.and_then(|exif| exif.captured_at),
+        }
+    }
+
+    fn row_count(&self) -> usize {
+        self.grid.row_count(self.filtered_photos.len())
+    }
+
+    /// Indices into `filtered_photos` of the photos laid out in the grid.
+    fn visible_photos(&self) -> Range<usize> {
+        let columns = self.grid.columns();
+        let rows = self.grid.visible_rows(self.row_count());
+        let end = (rows.end * columns).min(self.filtered_photos.len());
+        (rows.start * columns).min(end)..end
+    }
+
+    /// Scrolls the grid proportionally to the selected row, which always keeps that row in view.
+    fn scroll_to_selection(&mut self) -> Command<Message> {
+        let Some(index) = self.focused_index() else {
+            return Command::none();
+        };
+        let row_count = self.row_count();
+        let last_row = row_count.saturating_sub(1);
+        let y = if last_row == 0 {
+            0.0
+        } else {
+            (index / self.grid.columns()) as f32 / last_row as f32
+        };
+        // Snapping does not emit a scroll event, so track where it lands for virtualization.
+        self.grid.snap_to(y, row_count);
+
+        scrollable::snap_to(grid_scroll_id(), RelativeOffset { x: 0.0, y })
+    }
+
+    fn open_viewer(&mut self, id: PhotoId) -> Command<Message> {
+        if !self.photo_index.contains_key(&id) {
+            return Command::none();
         }
-        Command::none()
+        self.viewer = Some(ViewerState::new(id.clone()));
+        self.load_proxy(id)
     }
 
-    fn view(&self) -> Element<Message> {
-        let header = create_header();
-        let filters = create_filters(self);
-        
-        let content = if self.loading {
-            create_loading_view()
-        } else if self.filtered_photos.is_empty() {
-            create_empty_view()
+    /// Decodes the viewed photo into the proxy its previews are rendered from.
+    fn load_proxy(&self, id: PhotoId) -> Command<Message> {
+        let Some(&index) = self.photo_index.get(&id) else {
+            return Command::none();
+        };
+        let orientation = self.photos[index].exif.orientation;
+
+        Command::perform(viewer::load_proxy(id.clone(), orientation), move |result| {
+            Message::ProxyLoaded(id, result)
+        })
+    }
+
+    /// Decodes the viewed photo again after its file changed, dropping everything rendered
+    /// from the old decode.
+    fn reload_viewed(&mut self, id: PhotoId) -> Command<Message> {
+        let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id) else {
+            return Command::none();
+        };
+        viewer.original = None;
+        viewer.loading_original = false;
+        viewer.full_view = None;
+        if let Some(loupe) = &mut viewer.loupe {
+            loupe.patch = None;
+        }
+        let original = if viewer.loupe.is_some() || viewer.shows_full_resolution() {
+            self.load_original(id.clone())
         } else {
-            create_photo_grid(&self.filtered_photos, self.selected_photo)
+            Command::none()
         };
+        Command::batch([self.load_proxy(id), original])
+    }
 
-        Column::new()
-            .push(header)
-            .push(filters)
-            .push(content)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .into()
+    /// Decodes the viewed photo at full resolution for the loupe and the full view.
+    fn load_original(&mut self, id: PhotoId) -> Command<Message> {
+        let Some(&index) = self.photo_index.get(&id) else {
+            return Command::none();
+        };
+        let orientation = self.photos[index].exif.orientation;
+        let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id && !viewer.loading_original) else {
+            return Command::none();
+        };
+        viewer.loading_original = true;
+
+        Command::perform(viewer::load_original(id.clone(), orientation), move |result| {
+            Message::OriginalLoaded(id, result)
+        })
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        Subscription::none()
+    /// Renders the loupe region of the viewed photo with its current edits, one render at a time.
+    ///
+    /// Photos with framing edits have to be rendered whole for a new patch, which is too
+    /// slow to follow a slider, so those wait for the drag to finish.
+    fn load_loupe(&mut self, id: PhotoId) -> Command<Message> {
+        let edits = self.preview_edits(&id);
+        let editing = self.history.is_editing();
+        let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id && viewer.crop.is_none()) else {
+            return Command::none();
+        };
+        let (Some(original), Some(loupe)) = (viewer.original.clone(), &mut viewer.loupe) else {
+            return Command::none();
+        };
+        let reusable = loupe.patch.as_ref().is_some_and(|patch| patch.matches(&original, &edits, loupe.center));
+        if editing && !reusable && edits.iter().any(EditOp::is_framing) {
+            return Command::none();
+        }
+        if loupe.rendering {
+            loupe.render_pending = true;
+            return Command::none();
+        }
+        loupe.rendering = true;
+
+        Command::perform(viewer::render_loupe(original, loupe.patch.clone(), edits, loupe.center), move |result| {
+            Message::LoupeRendered(id, result)
+        })
     }
-}
 
-fn create_header() -> Container<'static, Message> {
-    let title = Text::new("POER")
-        .size(28)
-        .style(theme::Text::Color(Color::from_rgb(0.2, 0.5, 0.9)));
+    /// Renders the viewed photo at full resolution while it is zoomed in past the proxy,
+    /// decoding the original first. Waits for slider drags to finish, since full
+    /// resolution renders are too slow to follow them.
+    fn load_full_view(&mut self, id: PhotoId) -> Command<Message> {
+        let edits = self.preview_edits(&id);
+        let editing = self.history.is_editing();
+        let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id && viewer.shows_full_resolution()) else {
+            return Command::none();
+        };
+        if editing || viewer.full_view_rendering || viewer.full_view.as_ref().is_some_and(|view| view.edits == edits) {
+            return Command::none();
+        }
+        let Some(original) = viewer.original.clone() else {
+            return self.load_original(id);
+        };
+        viewer.full_view_rendering = true;
 
-    let subtitle = Text::new("Photo Organizer & Editor")
-        .size(14)
-        .style(theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)));
+        Command::perform(viewer::render_full_view(original, edits), move |result| Message::FullViewRendered(id, result))
+    }
 
-    let header_content = Row::new()
-        .push(
-            Column::new()
-                .push(title)
-                .push(subtitle)
-                .spacing(2)
-        )
-        .push(Space::with_width(Length::Fill))
-        .align_items(Alignment::Center)
-        .padding(Padding::new(20.0));
+    /// Renders the viewed photo with its current edits, one render at a time, and
+    /// refreshes the loupe and preset thumbnails.
+    fn load_preview(&mut self, id: PhotoId) -> Command<Message> {
+        let edits = self.preview_edits(&id);
+        let overlays = [
+            (Clipping::Shadows, self.show_shadow_clipping),
+            (Clipping::Highlights, self.show_highlight_clipping),
+        ]
+        .into_iter()
+        .filter_map(|(clipping, shown)| shown.then_some(clipping))
+        .collect();
+        let Some(viewer) = self.viewer.as_mut().filter(|viewer| viewer.photo == id) else {
+            return Command::none();
+        };
+        let Some(proxy) = viewer.proxy.clone() else {
+            return Command::none();
+        };
+        if viewer.rendering {
+            viewer.render_pending = true;
+            return Command::none();
+        }
+        viewer.rendering = true;
 
-    Container::new(header_content)
-        .width(Length::Fill)
-        .height(Length::Shrink)
-        .style(theme::Container::Custom(Box::new(HeaderStyle)))
-}
+        let preview = Command::perform(viewer::render_preview(proxy, edits.clone(), overlays), {
+            let id = id.clone();
+            move |result| Message::PreviewLoaded(id, edits, result)
+        });
+        Command::batch([preview, self.load_loupe(id.clone()), self.load_preset_thumbnails(id)])
+    }
 
-fn create_filters<'a>(app: &'a PhotoOrganizer) -> Container<'a, Message> {
-    let search_input = Text::new("Search:")
-        .size(14)
-        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)));
+    /// Renders the viewed photo with each preset applied, for the presets panel.
+    fn load_preset_thumbnails(&self, id: PhotoId) -> Command<Message> {
+        let edits = self.preview_edits(&id);
+        let Some(viewer) = self.viewer.as_ref().filter(|viewer| viewer.photo == id && viewer.crop.is_none()) else {
+            return Command::none();
+        };
+        let Some(proxy) = viewer.proxy.clone() else {
+            return Command::none();
+        };
+        let presets = self.presets.all().to_vec();
 
-    let search_field = TextInput::new("Search by filename...", &app.search_term)
-        .on_input(Message::SearchInput)
-        .padding(Padding::new(8.0));
+        Command::perform(viewer::render_preset_thumbnails(proxy, edits.clone(), presets), move |thumbnails| {
+            Message::PresetThumbnailsRendered(id, edits, thumbnails)
+        })
+    }
 
-    let mut file_type_filters = Row::new().spacing(10);
-    for (ext, &enabled) in &app.file_types {
-        let ext_clone = ext.clone();
-        file_type_filters = file_type_filters.push(
-            Checkbox::new(ext, enabled, move |_| Message::ToggleFileType(ext_clone.clone()))
-        );
+    /// Rotates or flips the files of `ids` in the background.
+    fn transform_photos(&self, ids: Vec<PhotoId>, transform: Transform) -> Command<Message> {
+        let mode = self.transform_mode;
+        Command::perform(transform::transform_files(ids, transform, mode), move |results| {
+            Message::FilesTransformed(transform, mode, results)
+        })
     }
 
-    let size_filters = Row::new().spacing(10)
-        .push(Text::new("Size:").size(14))
-        .push(
-            Radio::new("All", SizeFilter::All, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::All))
-        )
-        .push(
-            Radio::new("Small (<100K)", SizeFilter::Small, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::Small))
-        )
-        .push(
-            Radio::new("Medium (100K-500K)", SizeFilter::Medium, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::Medium))
-        )
-        .push(
-            Radio::new("Large (>500K)", SizeFilter::Large, Some(app.size_filter), |_| Message::SelectSizeFilter(SizeFilter::Large))
-        );
+    /// Re-reads the dimensions and orientation of rotated or flipped files right away rather
+    /// than waiting for the watcher, and saves their reframed edits.
+    fn transformed(&mut self, paths: Vec<PathBuf>) -> Command<Message> {
+        Command::batch([
+            Command::perform(watcher::probe_changes(paths, self.library.enabled_roots()), Message::ChangesProbed),
+            self.persist_edits(),
+        ])
+    }
 
-    let filters_column = Column::new()
-        .push(Row::new().push(search_input).push(search_field).spacing(10))
-        .push(file_type_filters)
-        .push(size_filters)
-        .push(create_sorting_controls(app))
-        .spacing(15)
-        .padding(Padding::new(20.0));
+    /// Edits the viewer shows `id` with: the crop tool previews the uncropped image
+    /// and the levels dialog the levels it has not applied yet.
+    fn preview_edits(&self, id: &Path) -> Vec<EditOp> {
+        if let Some(crop) = self.viewer.as_ref().and_then(|viewer| viewer.crop.as_ref()) {
+            return crop.preview_ops();
+        }
+        let mut ops = self.edits.get(id).to_vec();
+        if let Some(levels) = self.viewer.as_ref().and_then(|viewer| viewer.levels) {
+            replace_op(&mut ops, EditOp::Levels(levels));
+        }
+        ops
+    }
 
-    Container::new(filters_column)
-        .width(Length::Fill)
-        .style(theme::Container::Custom(Box::new(BackgroundStyle)))
-}
+    /// The viewed photo, unless the crop tool is open and owns its edit stack.
+    fn edited_photo(&self) -> Option<PhotoId> {
+        self.viewer.as_ref()
+            .filter(|viewer| viewer.crop.is_none())
+            .map(|viewer| viewer.photo.clone())
+    }
 
-fn create_loading_view() -> Element<'static, Message> {
-    let loading_text = Text::new("Loading photos...")
-        .size(16)
-        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)));
+    fn crop_session(&mut self) -> Option<&mut CropSession> {
+        self.viewer.as_mut().and_then(|viewer| viewer.crop.as_mut())
+    }
 
-    Container::new(loading_text)
-        .width(iced::Length::Fill)
-        .height(iced::Length::Fill)
-        .align_x(iced::alignment::Horizontal::Center)
-        .align_y(iced::alignment::Vertical::Center)
-        .into()
-}
+    /// Resets the crop to the largest one matching the selected aspect ratio.
+    fn fit_crop_to_ratio(&mut self) {
+        let Some(viewer) = &mut self.viewer else {
+            return;
+        };
+        if let (Some(crop), Some(preview)) = (&mut viewer.crop, &viewer.preview)
+            && let Some(ratio) = crop.ratio_value(preview.size)
+        {
+            crop.rect = fit_to_ratio(ratio, preview.size);
+        }
+    }
 
-fn create_empty_view() -> Element<'static, Message> {
-    let empty_text = Text::new("No photos found in your Pictures folder")
-        .size(16)
-        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)));
+    fn cancel_crop(&mut self) -> Command<Message> {
+        let Some(viewer) = &mut self.viewer else {
+            return Command::none();
+        };
+        viewer.crop = None;
+        let id = viewer.photo.clone();
+        self.load_preview(id)
+    }
 
-    Container::new(empty_text)
-        .width(iced::Length::Fill)
-        .height(iced::Length::Fill)
-        .align_x(iced::alignment::Horizontal::Center)
-        .align_y(iced::alignment::Vertical::Center)
-        .into()
-}
+    fn cancel_levels(&mut self) -> Command<Message> {
+        let Some(viewer) = &mut self.viewer else {
+            return Command::none();
+        };
+        viewer.levels = None;
+        let id = viewer.photo.clone();
+        self.load_preview(id)
+    }
 
-fn create_photo_grid(photos: &[Photo], selected: Option<usize>) -> Element<Message> {
-    let mut grid_content = Column::new().spacing(16).padding(Padding::new(20.0));
+    /// Replaces the curve of the channel shown in the tone curve editor.
+    fn set_curve(&mut self, id: &Path, curve: Curve) {
+        let Some(viewer) = &self.viewer else {
+            return;
+        };
+        let mut tone_curve = self.edits.tone_curve(id);
+        *tone_curve.curve_mut(viewer.curve_channel) = curve;
+        self.edits.replace(id, EditOp::Curves(tone_curve));
+    }
 
-    for (row_index, row_photos) in photos.chunks(6).enumerate() {
-        let mut row = Row::new().spacing(12);
+    /// Re-renders the viewed photo and records and saves the edit of `id`.
+    fn edits_changed(&mut self, id: PhotoId) -> Command<Message> {
+        Command::batch([self.load_preview(id), self.commit_edits()])
+    }
 
-        for (col_index, photo) in row_photos.iter().enumerate() {
-            let global_index = row_index * 6 + col_index;
-            let is_selected = selected == Some(global_index);
+    /// Records the finished edit in the history and saves the edit stacks.
+    fn commit_edits(&mut self) -> Command<Message> {
+        let finished = self.finish_edit();
+        let renders = match self.edited_photo() {
+            Some(id) => Command::batch([self.load_loupe(id.clone()), self.load_full_view(id)]),
+            None => Command::none(),
+        };
+        Command::batch([finished, self.persist_edits(), renders])
+    }
 
-            let photo_card = create_photo_card(photo, global_index, is_selected);
-            row = row.push(photo_card);
+    fn finish_edit(&mut self) -> Command<Message> {
+        match self.history.finish_edit(&self.edits) {
+            Some((label, action)) => self.record(label, action),
+            None => Command::none(),
         }
+    }
 
-        row = row.push(Space::with_width(Length::Fill));
-        grid_content = grid_content.push(row);
+    /// Adds a step to the history, permanently deleting trashed files that can no longer be restored.
+    fn record(&mut self, label: String, action: Action) -> Command<Message> {
+        // Recording discards the undone steps, so a jump still under way ends here.
+        self.history_target = None;
+        let expired = self.history.record(label, action);
+        if expired.is_empty() {
+            return Command::none();
+        }
+        Command::perform(file_ops::delete_files(expired), Message::TrashEmptied)
     }
 
-    let scrollable = Scrollable::new(grid_content)
-        .style(theme::Scrollable::Custom(Box::new(ScrollableStyle)));
+    /// Undoes or redoes steps until the history reaches its target, pausing while files are moved.
+    fn step_history(&mut self) -> Command<Message> {
+        let mut commands = Vec::new();
+        while !self.history_busy
+            && let Some(target) = self.history_target
+        {
+            let position = self.history.position();
+            let step = match target.cmp(&position) {
+                Ordering::Less => self.history.undo().map(|action| (action, true)),
+                Ordering::Greater => self.history.redo().map(|action| (action, false)),
+                Ordering::Equal => None,
+            };
+            let Some((action, undo)) = step else {
+                self.history_target = None;
+                break;
+            };
+            commands.push(self.apply_step(action, undo));
+        }
+        Command::batch(commands)
+    }
 
-    Container::new(scrollable)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .style(theme::Container::Custom(Box::new(BackgroundStyle)))
-        .into()
-}
+    /// Stops stepping through the history after the files of a step could not be moved,
+    /// leaving that step to be undone or redone again.
+    fn step_failed(&mut self, undo: bool, err: String) -> Command<Message> {
+        self.history.cancel_step();
+        self.history_target = None;
+        self.status_message = Some(format!("Could not {}: {}", if undo { "undo" } else { "redo" }, err));
+        Command::none()
+    }
+
+    /// Reverts `action` when `undo` is set, or applies it again.
+    fn apply_step(&mut self, action: Action, undo: bool) -> Command<Message> {
+        match action {
+            Action::Edit { photo, before, after } => {
+                self.edits.set(&photo, if undo { before } else { after });
+                let mut commands = vec![self.persist_edits()];
+                if let Some(viewer) = &mut self.viewer
+                    && viewer.photo == photo
+                {
+                    viewer.crop = None;
+                    viewer.levels = None;
+                    commands.push(self.load_preview(photo));
+                }
+                Command::batch(commands)
+            }
+            Action::Edits { before, after } => {
+                let changes = if undo { before } else { after };
+                let viewed = self.viewer.as_ref()
+                    .map(|viewer| viewer.photo.clone())
+                    .filter(|photo| changes.iter().any(|(path, _)| path == photo));
+                for (path, ops) in changes {
+                    self.edits.set(&path, ops);
+                }
+                let mut commands = vec![self.persist_edits()];
+                if let Some(photo) = viewed {
+                    if let Some(viewer) = &mut self.viewer {
+                        viewer.crop = None;
+                        viewer.levels = None;
+                    }
+                    commands.push(self.load_preview(photo));
+                }
+                Command::batch(commands)
+            }
+            Action::Annotate { before, after } => {
+                for (path, annotation) in if undo { before } else { after } {
+                    self.annotations.set(&path, annotation);
+                }
+                self.apply_filters();
+                self.persist_annotations()
+            }
+            Action::Move { moves } => {
+                let moves = if undo {
+                    moves.into_iter().map(|(from, to)| (to, from)).collect()
+                } else {
+                    moves
+                };
+                self.history_busy = true;
+                Command::perform(file_ops::relocate_files(moves), move |result| Message::HistoryFilesMoved(undo, result))
+            }
+            Action::Delete { files } => {
+                self.history_busy = true;
+                if undo {
+                    let moves = files.iter().map(|file| (file.trashed.clone(), file.original.clone())).collect();
+                    Command::perform(file_ops::relocate_files(moves), move |result| Message::HistoryFilesRestored(files, result))
+                } else {
+                    let moves = files.into_iter().map(|file| (file.original, file.trashed)).collect();
+                    Command::perform(file_ops::relocate_files(moves), Message::HistoryFilesTrashed)
+                }
+            }
+            Action::Transform { transform, mode, files, before, after } => {
+                let (transform, edits) = if undo { (transform.inverse(), before) } else { (transform, after) };
+                self.history_busy = true;
+                Command::perform(transform::transform_all(files, transform, mode), move |result| {
+                    Message::HistoryFilesTransformed(undo, edits, result)
+                })
+            }
+        }
+    }
 
-struct PhotoCardStyle;
+    /// Applies `change` to every selected photo and records it as one step.
+    fn annotate_selection(&mut self, label: String, change: impl Fn(&mut Annotations, &Path)) -> Command<Message> {
+        let paths = self.selected_paths();
+        let snapshot = |annotations: &Annotations| {
+            paths.iter()
+                .map(|path| (path.clone(), annotations.get(path).cloned()))
+                .collect::<Vec<_>>()
+        };
+        let before = snapshot(&self.annotations);
+        for path in &paths {
+            change(&mut self.annotations, path);
+        }
+        let after = snapshot(&self.annotations);
+        if before == after {
+            return Command::none();
+        }
+        self.apply_filters();
+        Command::batch([self.record(label, Action::Annotate { before, after }), self.persist_annotations()])
+    }
 
-impl iced::widget::button::StyleSheet for PhotoCardStyle {
-    type Style = iced::Theme;
+    fn persist_edits(&self) -> Command<Message> {
+        Command::perform(save_edits(self.edits.clone(), SaveTicket::take()), Message::EditsSaved)
+    }
 
-    fn active(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
-        let _palette = theme.extended_palette();
-        
-        iced::widget::button::Appearance {
-            background: Some(iced::Background::Color(Color::from_rgba(0.95, 0.95, 0.95, 0.8))),
-            border_color: Color::from_rgb(0.7, 0.7, 0.7),
-            border_width: 1.0,
-            shadow_offset: iced::Vector::new(0.0, 3.0),
-            ..Default::default()
+    fn library_changed(&mut self) -> Command<Message> {
+        if let Err(err) = self.library.save() {
+            eprintln!("Failed to save library settings: {}", err);
         }
+        self.start_scan();
+        Command::none()
     }
 
-    fn hovered(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
-        let _palette = theme.extended_palette();
-        
-        iced::widget::button::Appearance {
-            background: Some(iced::Background::Color(Color::from_rgba(0.98, 0.98, 0.98, 0.9))),
-            border_color: Color::from_rgb(0.4, 0.6, 0.8),
-            shadow_offset: iced::Vector::new(0.0, 6.0),
-            ..Default::default()
+    /// Rescans the enabled library roots, reusing already indexed photos whose files are unchanged.
+    fn start_scan(&mut self) {
+        let roots = self.library.enabled_roots();
+        self.retain_photos(|photo| roots.iter().any(|root| photo.path.starts_with(&root.path)));
+
+        let known = self.photos.iter()
+            .map(|photo| (photo.path.clone(), photo.clone()))
+            .collect();
+
+        self.scan_count += 1;
+        self.scan = Some(ScanState {
+            id: self.scan_count,
+            progress: ScanProgress::default(),
+            known: Arc::new(known),
+            seen: HashSet::new(),
+            filtered_at: Instant::now(),
+            unfiltered: false,
+        });
+        self.apply_filters();
+    }
+
+    /// Adds new photos and replaces changed ones, returning whether anything was modified.
+    fn upsert_photos(&mut self, photos: Vec<Photo>) -> bool {
+        let mut changed = false;
+        for photo in photos {
+            match self.photo_index.get(&photo.path) {
+                Some(&index) => {
+                    if self.photos[index] != photo {
+                        self.photos[index] = photo;
+                        changed = true;
+                    }
+                }
+                None => {
+                    self.photo_index.insert(photo.path.clone(), self.photos.len());
+                    self.photos.push(photo);
+                    changed = true;
+                }
+            }
         }
+        changed
     }
 
-    fn pressed(&self, theme: &Self::Style) -> iced::widget::button::Appearance {
-        let _palette = theme.extended_palette();
-        
-        iced::widget::button::Appearance {
-            background: Some(iced::Background::Color(Color::from_rgba(0.92, 0.92, 0.92, 0.85))),
-            border_color: Color::from_rgb(0.3, 0.5, 0.7),
-            shadow_offset: iced::Vector::new(0.0, 2.0),
-            ..Default::default()
+    /// Drops every photo `keep` rejects, returning whether any were removed.
+    fn retain_photos(&mut self, keep: impl FnMut(&Photo) -> bool) -> bool {
+        let count = self.photos.len();
+        self.photos.retain(keep);
+        if self.photos.len() == count {
+            return false;
         }
+
+        self.photo_index = self.photos.iter()
+            .enumerate()
+            .map(|(index, photo)| (photo.path.clone(), index))
+            .collect();
+        true
     }
-}
 
-fn create_photo_card(photo: &Photo, index: usize, is_selected: bool) -> Button<Message> {
-    let image = Image::new(photo.path.clone())
-        .width(180)
-        .height(120);
+    /// Points the photo at `from` to its new location after a move.
+    fn rename_photo(&mut self, from: &Path, to: &Path) {
+        if let Some(index) = self.photo_index.remove(from) {
+            let photo = &mut self.photos[index];
+            photo.path = to.to_path_buf();
+            photo.name = to.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            self.photo_index.insert(photo.path.clone(), index);
+        }
+    }
 
-    let filename = Text::new(&photo.name)
-        .size(14)
-        .style(theme::Text::Color(Color::from_rgb(0.2, 0.2, 0.2)));
+    /// Carries photos and their user data over to where the files were moved, returning
+    /// the moves that succeeded.
+    fn apply_moves(&mut self, results: &[FileResult]) -> Vec<(PathBuf, PathBuf)> {
+        let moves = results.iter()
+            .filter_map(|(from, result)| result.as_ref().ok().map(|to| (from.clone(), to.clone())))
+            .collect::<Vec<_>>();
+        for (from, to) in &moves {
+            self.rename_photo(from, to);
+            self.annotations.rename(from, to);
+            self.edits.rename(from, to);
+            if let Some(viewer) = &mut self.viewer
+                && &viewer.photo == from
+            {
+                viewer.photo = to.clone();
+            }
+        }
+        moves
+    }
 
-    let card_content = Column::new()
-        .push(image)
-        .push(filename)
-        .spacing(12)
-        .align_items(Alignment::Center)
-        .padding(Padding::new(12.0));
+    /// Drops the photos that were deleted along with their user data.
+    fn remove_deleted(&mut self, results: &[FileResult]) {
+        let deleted = results.iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(path, _)| path.clone())
+            .collect::<HashSet<_>>();
+        self.retain_photos(|photo| !deleted.contains(&photo.path));
+        for path in &deleted {
+            self.annotations.remove(path);
+            self.edits.remove(path);
+        }
+    }
 
-    Button::new(card_content)
-        .width(200)
-        .height(240)
-        .padding(Padding::new(0.0))
-        .style(theme::Button::Custom(Box::new(PhotoCardStyle)))
-        .on_press(if is_selected {
-            Message::PhotoDeselected
-        } else {
-            Message::PhotoSelected(index)
-        })
-}
+    /// Refreshes the grid and saves user data after photos were moved or deleted on disk.
+    fn files_changed(&mut self) -> Command<Message> {
+        self.selected_photo = None;
+        self.selection.clear();
+        self.apply_filters();
+        Command::batch([self.persist_catalog(), self.persist_annotations(), self.persist_edits()])
+    }
 
-fn create_sorting_controls(app: &PhotoOrganizer) -> Container<'static, Message> {
-    let sort_criteria = Row::new()
-        .push(Text::new("Sort by:").size(14))
-        .push(
-            Radio::new("Name", SortCriteria::Name, Some(app.sort_criteria), move |v| Message::SortCriteriaChanged(v))
-        )
-        .push(
-            Radio::new("Date", SortCriteria::Date, Some(app.sort_criteria), move |v| Message::SortCriteriaChanged(v))
-        )
-        .push(
-            Radio::new("Size", SortCriteria::Size, Some(app.sort_criteria), move |v| Message::SortCriteriaChanged(v))
-        );
+    fn persist_presets(&self) -> Command<Message> {
+        Command::perform(save_presets(self.presets.clone(), SaveTicket::take()), Message::PresetsSaved)
+    }
 
-    let sort_order = Button::new(
-        Text::new(match app.sort_order {
-            SortOrder::Ascending => "↑ Ascending",
-            SortOrder::Descending => "↓ Descending",
-        })
-    )
-    .on_press(Message::ToggleSortOrder);
+    fn persist_annotations(&self) -> Command<Message> {
+        Command::perform(save_annotations(self.annotations.clone(), SaveTicket::take()), Message::AnnotationsSaved)
+    }
 
-    Container::new(
-        Column::new()
-            .push(sort_criteria)
-            .push(sort_order)
-            .spacing(15)
-    )
-    .width(Length::Fill)
-    .style(theme::Container::Custom(Box::new(BackgroundStyle)))
-}
+    /// Saves the catalog in the background, deferring to the end of any running scan.
+    fn persist_catalog(&self) -> Command<Message> {
+        if self.scan.is_some() {
+            return Command::none();
+        }
+        Command::perform(save_catalog(self.photos.clone(), SaveTicket::take()), Message::CatalogSaved)
+    }
 
-impl PhotoOrganizer {
     fn apply_filters(&mut self) {
         let search_term = self.search_term.to_lowercase();
 
@@ -400,6 +3375,9 @@ impl PhotoOrganizer {
             .filter(|photo| {
                 if !search_term.is_empty() {
                     photo.name.to_lowercase().contains(&search_term)
+                        || self.annotations.get(&photo.path).is_some_and(|annotation| {
+                            annotation.tags.iter().any(|tag| tag.to_lowercase().contains(&search_term))
+                        })
                 } else {
                     true
                 }
@@ -423,17 +3401,7 @@ impl PhotoOrganizer {
             .cloned()
             .collect::<Vec<Photo>>();
 
-        let mut photo_times: HashMap<PathBuf, SystemTime> = HashMap::new();
-
         let mut sorted_filtered = filtered;
-        
-        for photo in &sorted_filtered {
-            if let Ok(metadata) = std::fs::metadata(&photo.path) {
-                if let Ok(modified) = metadata.modified() {
-                    photo_times.insert(photo.path.clone(), modified);
-                }
-            }
-        }
 
         match (self.sort_criteria, self.sort_order) {
             (SortCriteria::Name, SortOrder::Ascending) => {
@@ -442,35 +3410,43 @@ impl PhotoOrganizer {
             (SortCriteria::Name, SortOrder::Descending) => {
                 sorted_filtered.sort_by(|a, b| b.name.cmp(&a.name));
             }
-            (SortCriteria::Date, SortOrder::Ascending) => {
-                sorted_filtered.sort_by(|a, b| {
-                    let a_time = photo_times.get(&a.path);
-                    let b_time = photo_times.get(&b.path);
-                    match (a_time, b_time) {
-                        (Some(a), Some(b)) => a.cmp(&b),
-                        _ => Ordering::Equal,
-                    }
-                });
-            }
-            (SortCriteria::Date, SortOrder::Descending) => {
+            (SortCriteria::Date, order) => {
+                // Undated photos always go last, in name order, whichever direction is chosen.
                 sorted_filtered.sort_by(|a, b| {
-                    let a_time = photo_times.get(&a.path);
-                    let b_time = photo_times.get(&b.path);
-                    match (a_time, b_time) {
-                        (Some(a), Some(b)) => b.cmp(&a),
-                        _ => Ordering::Equal,
+                    match (a.date_taken(), b.date_taken()) {
+                        (Some(a_date), Some(b_date)) => match order {
+                            SortOrder::Ascending => a_date.cmp(&b_date),
+                            SortOrder::Descending => b_date.cmp(&a_date),
+                        },
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
                     }
+                    .then_with(|| a.name.cmp(&b.name))
                 });
             }
             (SortCriteria::Size, SortOrder::Ascending) => {
-                sorted_filtered.sort_by(|a, b| a.size.cmp(&b.size));
+                sorted_filtered.sort_by_key(|photo| photo.size);
             }
             (SortCriteria::Size, SortOrder::Descending) => {
-                sorted_filtered.sort_by(|a, b| b.size.cmp(&a.size));
+                sorted_filtered.sort_by_key(|photo| std::cmp::Reverse(photo.size));
             }
         }
         
         self.filtered_photos = sorted_filtered;
-        self.row_count = (self.filtered_photos.len() + 5) / 6;
+        self.filtered_index = self.filtered_photos.iter()
+            .enumerate()
+            .map(|(index, photo)| (photo.path.clone(), index))
+            .collect();
+
+        // Forget photos that no longer exist; those merely filtered out stay selected.
+        let photo_index = &self.photo_index;
+        self.selection.retain(|id| photo_index.contains_key(id));
+        if self.selected_photo.as_ref().is_some_and(|id| !photo_index.contains_key(id)) {
+            self.selected_photo = None;
+        }
+        if self.viewer.as_ref().is_some_and(|viewer| !photo_index.contains_key(&viewer.photo)) {
+            self.viewer = None;
+        }
     }
 }
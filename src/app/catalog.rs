@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::app::file_ops::{write_store, SaveTicket};
+use crate::app::photo_loader::Photo;
+
+const CATALOG_FILE: &str = "catalog.json";
+/// Bump whenever the stored [`Photo`] layout changes so stale catalogs are rebuilt.
+const CATALOG_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize)]
+struct Catalog {
+    version: u32,
+    photos: Vec<Photo>,
+}
+
+/// Loads the photos indexed by a previous session, or nothing if no usable catalog exists.
+pub fn load_catalog() -> Vec<Photo> {
+    catalog_path()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|contents| serde_json::from_slice::<Catalog>(&contents).ok())
+        .filter(|catalog| catalog.version == CATALOG_VERSION)
+        .map(|catalog| catalog.photos)
+        .unwrap_or_default()
+}
+
+pub async fn save_catalog(photos: Vec<Photo>, ticket: SaveTicket) -> Result<(), String> {
+    write_catalog(photos, ticket).map_err(|err| err.to_string())
+}
+
+fn write_catalog(photos: Vec<Photo>, ticket: SaveTicket) -> io::Result<()> {
+    let path = catalog_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+
+    let catalog = Catalog {
+        version: CATALOG_VERSION,
+        photos,
+    };
+    let contents = serde_json::to_vec(&catalog).map_err(io::Error::other)?;
+    write_store(&path, &contents, ticket)
+}
+
+fn catalog_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("poer").join(CATALOG_FILE))
+}
@@ -0,0 +1,474 @@
+use iced::advanced::image;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Quad};
+use iced::advanced::widget::tree::{self, Tree};
+use iced::advanced::{Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::mouse;
+use iced::{Color, Element, Length, Point, Rectangle, Size};
+use std::fmt;
+
+use crate::app::edit::CropRect;
+use crate::app::photo_view::fit_scale;
+
+/// Smallest crop, as a fraction of the image on either side.
+const MIN_CROP: f32 = 0.05;
+/// Distance in pixels within which a click grabs an edge or corner.
+const GRIP_TOLERANCE: f32 = 10.0;
+/// Side of the square drawn on each corner.
+const HANDLE_SIZE: f32 = 10.0;
+/// Free space kept around the image so the handles stay reachable.
+const MARGIN: f32 = 24.0;
+
+/// The uncropped image.
+pub const FULL_CROP: CropRect = CropRect { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+/// Aspect ratio the crop rectangle is locked to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AspectRatio {
+    Free,
+    Original,
+    Square,
+    FourThree,
+    ThreeTwo,
+    SixteenNine,
+    Custom,
+}
+
+impl AspectRatio {
+    pub const ALL: [AspectRatio; 7] = [
+        AspectRatio::Free,
+        AspectRatio::Original,
+        AspectRatio::Square,
+        AspectRatio::FourThree,
+        AspectRatio::ThreeTwo,
+        AspectRatio::SixteenNine,
+        AspectRatio::Custom,
+    ];
+
+    /// Width over height in pixels for an image of `image_size`, or `None` when unconstrained.
+    ///
+    /// Preset ratios follow the orientation of the image, so 3:2 becomes 2:3 on a portrait photo.
+    pub fn value(self, image_size: Size, custom: Option<f32>) -> Option<f32> {
+        let landscape = |ratio: f32| {
+            if image_size.height > image_size.width { 1.0 / ratio } else { ratio }
+        };
+        match self {
+            AspectRatio::Free => None,
+            AspectRatio::Original => Some(image_size.width / image_size.height),
+            AspectRatio::Square => Some(1.0),
+            AspectRatio::FourThree => Some(landscape(4.0 / 3.0)),
+            AspectRatio::ThreeTwo => Some(landscape(3.0 / 2.0)),
+            AspectRatio::SixteenNine => Some(landscape(16.0 / 9.0)),
+            AspectRatio::Custom => custom,
+        }
+        .filter(|ratio| ratio.is_finite() && *ratio > 0.0)
+    }
+}
+
+impl fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AspectRatio::Free => "Free",
+            AspectRatio::Original => "Original",
+            AspectRatio::Square => "1:1",
+            AspectRatio::FourThree => "4:3",
+            AspectRatio::ThreeTwo => "3:2",
+            AspectRatio::SixteenNine => "16:9",
+            AspectRatio::Custom => "Custom",
+        })
+    }
+}
+
+/// Largest crop with a pixel aspect `ratio` centred on an image of `image_size`.
+pub fn fit_to_ratio(ratio: f32, image_size: Size) -> CropRect {
+    let fraction_ratio = ratio * image_size.height / image_size.width;
+    let width = fraction_ratio.min(1.0);
+    let height = width / fraction_ratio;
+    CropRect {
+        x: (1.0 - width) / 2.0,
+        y: (1.0 - height) / 2.0,
+        width,
+        height,
+    }
+}
+
+/// Part of the crop rectangle a drag started on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Grip {
+    Move,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Grip {
+    /// Direction each axis grows in when dragged: `1.0` for the right or bottom side,
+    /// `-1.0` for the left or top side and `None` if the axis is not dragged.
+    fn directions(self) -> (Option<f32>, Option<f32>) {
+        match self {
+            Grip::Move => (None, None),
+            Grip::Left => (Some(-1.0), None),
+            Grip::Right => (Some(1.0), None),
+            Grip::Top => (None, Some(-1.0)),
+            Grip::Bottom => (None, Some(1.0)),
+            Grip::TopLeft => (Some(-1.0), Some(-1.0)),
+            Grip::TopRight => (Some(1.0), Some(-1.0)),
+            Grip::BottomLeft => (Some(-1.0), Some(1.0)),
+            Grip::BottomRight => (Some(1.0), Some(1.0)),
+        }
+    }
+}
+
+/// Shows a whole image with a draggable crop rectangle and rule-of-thirds guides.
+///
+/// The rectangle is owned by the application and changes are reported through `on_change`.
+pub struct CropView<'a, Message, Handle> {
+    handle: Handle,
+    image_size: Size,
+    rect: CropRect,
+    ratio: Option<f32>,
+    on_change: Box<dyn Fn(CropRect) -> Message + 'a>,
+}
+
+impl<'a, Message, Handle> CropView<'a, Message, Handle> {
+    /// `ratio` locks the crop to a width over height in image pixels.
+    pub fn new(
+        handle: Handle,
+        image_size: Size,
+        rect: CropRect,
+        ratio: Option<f32>,
+        on_change: impl Fn(CropRect) -> Message + 'a,
+    ) -> Self {
+        CropView {
+            handle,
+            image_size,
+            rect,
+            ratio,
+            on_change: Box::new(on_change),
+        }
+    }
+
+    fn image_bounds(&self, bounds: Rectangle) -> Rectangle {
+        let available = Size::new((bounds.width - 2.0 * MARGIN).max(1.0), (bounds.height - 2.0 * MARGIN).max(1.0));
+        let scale = fit_scale(self.image_size, available);
+        let size = Size::new(self.image_size.width * scale, self.image_size.height * scale);
+        let center = bounds.center();
+
+        Rectangle {
+            x: center.x - size.width / 2.0,
+            y: center.y - size.height / 2.0,
+            width: size.width,
+            height: size.height,
+        }
+    }
+
+    fn crop_bounds(&self, image_bounds: Rectangle) -> Rectangle {
+        Rectangle {
+            x: image_bounds.x + self.rect.x * image_bounds.width,
+            y: image_bounds.y + self.rect.y * image_bounds.height,
+            width: self.rect.width * image_bounds.width,
+            height: self.rect.height * image_bounds.height,
+        }
+    }
+
+    fn grip_at(&self, position: Point, image_bounds: Rectangle) -> Option<Grip> {
+        let crop = self.crop_bounds(image_bounds);
+        let near = |a: f32, b: f32| (a - b).abs() <= GRIP_TOLERANCE;
+        let within = |value: f32, start: f32, end: f32| value >= start - GRIP_TOLERANCE && value <= end + GRIP_TOLERANCE;
+        if !within(position.x, crop.x, crop.x + crop.width) || !within(position.y, crop.y, crop.y + crop.height) {
+            return None;
+        }
+
+        let left = near(position.x, crop.x);
+        let right = near(position.x, crop.x + crop.width);
+        let top = near(position.y, crop.y);
+        let bottom = near(position.y, crop.y + crop.height);
+
+        Some(match (left, right, top, bottom) {
+            (true, _, true, _) => Grip::TopLeft,
+            (_, true, true, _) => Grip::TopRight,
+            (true, _, _, true) => Grip::BottomLeft,
+            (_, true, _, true) => Grip::BottomRight,
+            (true, ..) => Grip::Left,
+            (_, true, ..) => Grip::Right,
+            (_, _, true, _) => Grip::Top,
+            (.., true) => Grip::Bottom,
+            _ => Grip::Move,
+        })
+    }
+
+    /// Crop after dragging `grip` of `start` to `point`, both in image fractions.
+    fn drag(&self, grip: Grip, start: CropRect, origin: Point, point: Point) -> CropRect {
+        if grip == Grip::Move {
+            return CropRect {
+                x: (start.x + point.x - origin.x).clamp(0.0, 1.0 - start.width),
+                y: (start.y + point.y - origin.y).clamp(0.0, 1.0 - start.height),
+                ..start
+            };
+        }
+
+        let (x_direction, y_direction) = grip.directions();
+        let center_x = start.x + start.width / 2.0;
+        let center_y = start.y + start.height / 2.0;
+        // The edge opposite the dragged one stays put; an undragged axis stays centred.
+        let anchor_x = match x_direction {
+            Some(direction) if direction > 0.0 => start.x,
+            Some(_) => start.x + start.width,
+            None => center_x,
+        };
+        let anchor_y = match y_direction {
+            Some(direction) if direction > 0.0 => start.y,
+            Some(_) => start.y + start.height,
+            None => center_y,
+        };
+
+        let mut width = x_direction.map_or(start.width, |direction| ((point.x - anchor_x) * direction).max(MIN_CROP));
+        let mut height = y_direction.map_or(start.height, |direction| ((point.y - anchor_y) * direction).max(MIN_CROP));
+        let max_width = match x_direction {
+            Some(direction) if direction > 0.0 => 1.0 - anchor_x,
+            Some(_) => anchor_x,
+            None => 2.0 * center_x.min(1.0 - center_x),
+        };
+        let max_height = match y_direction {
+            Some(direction) if direction > 0.0 => 1.0 - anchor_y,
+            Some(_) => anchor_y,
+            None => 2.0 * center_y.min(1.0 - center_y),
+        };
+
+        match self.ratio {
+            Some(ratio) => {
+                let fraction_ratio = ratio * self.image_size.height / self.image_size.width;
+                match (x_direction, y_direction) {
+                    (Some(_), Some(_)) if width / height > fraction_ratio => height = width / fraction_ratio,
+                    (Some(_), Some(_)) => width = height * fraction_ratio,
+                    (Some(_), None) => height = width / fraction_ratio,
+                    _ => width = height * fraction_ratio,
+                }
+                let shrink = (max_width / width).min(max_height / height).min(1.0);
+                width *= shrink;
+                height *= shrink;
+            }
+            None => {
+                width = width.min(max_width);
+                height = height.min(max_height);
+            }
+        }
+
+        let place = |direction: Option<f32>, anchor: f32, size: f32| match direction {
+            Some(direction) if direction > 0.0 => anchor,
+            Some(_) => anchor - size,
+            None => anchor - size / 2.0,
+        };
+        CropRect {
+            x: place(x_direction, anchor_x, width),
+            y: place(y_direction, anchor_y, height),
+            width,
+            height,
+        }
+    }
+}
+
+fn to_fraction(position: Point, image_bounds: Rectangle) -> Point {
+    Point::new(
+        (position.x - image_bounds.x) / image_bounds.width,
+        (position.y - image_bounds.y) / image_bounds.height,
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    grip: Grip,
+    origin: Point,
+    start: CropRect,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    drag: Option<Drag>,
+}
+
+impl<'a, Message, Renderer, Handle> Widget<Message, Renderer> for CropView<'a, Message, Handle>
+where
+    Renderer: image::Renderer<Handle = Handle>,
+    Handle: Clone + std::hash::Hash,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Fill
+    }
+
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(limits.width(Length::Fill).height(Length::Fill).resolve(Size::ZERO))
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let image_bounds = self.image_bounds(layout.bounds());
+        let state = tree.state.downcast_mut::<State>();
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(position) = cursor.position_over(layout.bounds()) else {
+                    return event::Status::Ignored;
+                };
+                let Some(grip) = self.grip_at(position, image_bounds) else {
+                    return event::Status::Ignored;
+                };
+                state.drag = Some(Drag {
+                    grip,
+                    origin: to_fraction(position, image_bounds),
+                    start: self.rect,
+                });
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                if state.drag.take().is_some() {
+                    event::Status::Captured
+                } else {
+                    event::Status::Ignored
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                let Some(drag) = state.drag else {
+                    return event::Status::Ignored;
+                };
+                let point = to_fraction(position, image_bounds);
+                shell.publish((self.on_change)(self.drag(drag.grip, drag.start, drag.origin, point)));
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let state = tree.state.downcast_ref::<State>();
+        let grip = match state.drag {
+            Some(drag) => Some(drag.grip),
+            None => cursor
+                .position_over(layout.bounds())
+                .and_then(|position| self.grip_at(position, self.image_bounds(layout.bounds()))),
+        };
+
+        match grip {
+            Some(Grip::Move) if state.drag.is_some() => mouse::Interaction::Grabbing,
+            Some(Grip::Move) => mouse::Interaction::Grab,
+            Some(Grip::Left | Grip::Right) => mouse::Interaction::ResizingHorizontally,
+            Some(Grip::Top | Grip::Bottom) => mouse::Interaction::ResizingVertically,
+            Some(_) => mouse::Interaction::Crosshair,
+            None => mouse::Interaction::Idle,
+        }
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Renderer::Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let image_bounds = self.image_bounds(bounds);
+        let crop = self.crop_bounds(image_bounds);
+
+        renderer.with_layer(bounds, |renderer| {
+            image::Renderer::draw(renderer, self.handle.clone(), image_bounds);
+        });
+
+        renderer.with_layer(bounds, |renderer| {
+            let fill = |renderer: &mut Renderer, bounds: Rectangle, color: Color| {
+                renderer.fill_quad(
+                    Quad {
+                        bounds,
+                        border_radius: 0.0.into(),
+                        border_width: 0.0,
+                        border_color: Color::TRANSPARENT,
+                    },
+                    color,
+                );
+            };
+
+            // Dim everything outside the crop.
+            let shade = Color::from_rgba(0.0, 0.0, 0.0, 0.55);
+            let image_right = image_bounds.x + image_bounds.width;
+            let image_bottom = image_bounds.y + image_bounds.height;
+            let crop_right = crop.x + crop.width;
+            let crop_bottom = crop.y + crop.height;
+            fill(renderer, Rectangle::new(image_bounds.position(), Size::new(image_bounds.width, crop.y - image_bounds.y)), shade);
+            fill(renderer, Rectangle::new(Point::new(image_bounds.x, crop_bottom), Size::new(image_bounds.width, image_bottom - crop_bottom)), shade);
+            fill(renderer, Rectangle::new(Point::new(image_bounds.x, crop.y), Size::new(crop.x - image_bounds.x, crop.height)), shade);
+            fill(renderer, Rectangle::new(Point::new(crop_right, crop.y), Size::new(image_right - crop_right, crop.height)), shade);
+
+            // Rule-of-thirds guides.
+            let guide = Color::from_rgba(1.0, 1.0, 1.0, 0.5);
+            for third in [1.0 / 3.0, 2.0 / 3.0] {
+                fill(renderer, Rectangle::new(Point::new(crop.x + crop.width * third, crop.y), Size::new(1.0, crop.height)), guide);
+                fill(renderer, Rectangle::new(Point::new(crop.x, crop.y + crop.height * third), Size::new(crop.width, 1.0)), guide);
+            }
+
+            renderer.fill_quad(
+                Quad {
+                    bounds: crop,
+                    border_radius: 0.0.into(),
+                    border_width: 1.5,
+                    border_color: Color::WHITE,
+                },
+                Color::TRANSPARENT,
+            );
+
+            for (x, y) in [(crop.x, crop.y), (crop_right, crop.y), (crop.x, crop_bottom), (crop_right, crop_bottom)] {
+                let handle = Rectangle::new(
+                    Point::new(x - HANDLE_SIZE / 2.0, y - HANDLE_SIZE / 2.0),
+                    Size::new(HANDLE_SIZE, HANDLE_SIZE),
+                );
+                fill(renderer, handle, Color::WHITE);
+            }
+        });
+    }
+}
+
+impl<'a, Message, Renderer, Handle> From<CropView<'a, Message, Handle>> for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: image::Renderer<Handle = Handle> + 'a,
+    Handle: Clone + std::hash::Hash + 'a,
+{
+    fn from(view: CropView<'a, Message, Handle>) -> Self {
+        Element::new(view)
+    }
+}
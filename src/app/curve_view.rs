@@ -0,0 +1,266 @@
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Quad};
+use iced::advanced::widget::tree::{self, Tree};
+use iced::advanced::{Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::mouse;
+use iced::{Color, Element, Length, Point, Rectangle, Size};
+
+use crate::app::histogram_view::{draw_bars, fill};
+use crate::app::tone::Curve;
+
+/// Distance in pixels within which a click grabs a control point.
+const GRIP_TOLERANCE: f32 = 8.0;
+/// Side of the square drawn on each control point.
+const HANDLE_SIZE: f32 = 8.0;
+/// Free space kept around the plot so the end points stay reachable.
+const MARGIN: f32 = 6.0;
+/// Thickness of the curve line.
+const LINE_WIDTH: f32 = 2.0;
+
+/// Edits a tone curve by dragging its control points, drawn over a histogram.
+///
+/// Clicking the plot adds a point, right-clicking a point removes it. The curve is owned
+/// by the application: drags report a new curve through `on_change` and `on_release`
+/// is sent once a change is complete.
+pub struct CurveEditor<'a, Message> {
+    curve: Curve,
+    histogram: Option<&'a [u32; 256]>,
+    color: Color,
+    on_change: Box<dyn Fn(Curve) -> Message + 'a>,
+    on_release: Message,
+}
+
+impl<'a, Message> CurveEditor<'a, Message> {
+    pub fn new(
+        curve: &Curve,
+        histogram: Option<&'a [u32; 256]>,
+        color: Color,
+        on_change: impl Fn(Curve) -> Message + 'a,
+        on_release: Message,
+    ) -> Self {
+        CurveEditor {
+            curve: curve.clone(),
+            histogram,
+            color,
+            on_change: Box::new(on_change),
+            on_release,
+        }
+    }
+
+    fn plot_bounds(bounds: Rectangle) -> Rectangle {
+        Rectangle {
+            x: bounds.x + MARGIN,
+            y: bounds.y + MARGIN,
+            width: (bounds.width - 2.0 * MARGIN).max(1.0),
+            height: (bounds.height - 2.0 * MARGIN).max(1.0),
+        }
+    }
+
+    fn point_at(&self, position: Point, plot: Rectangle) -> Option<usize> {
+        self.curve
+            .points()
+            .iter()
+            .map(|&point| to_screen(point, plot))
+            .enumerate()
+            .map(|(index, point)| (index, point.distance(position)))
+            .filter(|&(_, distance)| distance <= GRIP_TOLERANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+}
+
+fn to_screen((x, y): (f32, f32), plot: Rectangle) -> Point {
+    Point::new(plot.x + x * plot.width, plot.y + (1.0 - y) * plot.height)
+}
+
+fn to_curve(position: Point, plot: Rectangle) -> (f32, f32) {
+    (
+        ((position.x - plot.x) / plot.width).clamp(0.0, 1.0),
+        (1.0 - (position.y - plot.y) / plot.height).clamp(0.0, 1.0),
+    )
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Index of the point being dragged.
+    drag: Option<usize>,
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for CurveEditor<'a, Message>
+where
+    Message: Clone,
+    Renderer: renderer::Renderer,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Shrink
+    }
+
+    /// A square as wide as the space available.
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        let side = limits.width(Length::Fill).resolve(Size::ZERO).width;
+        layout::Node::new(Size::new(side, side))
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        let plot = Self::plot_bounds(bounds);
+        let state = tree.state.downcast_mut::<State>();
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(position) = cursor.position_over(bounds) else {
+                    return event::Status::Ignored;
+                };
+                if let Some(index) = self.point_at(position, plot) {
+                    state.drag = Some(index);
+                } else {
+                    let (x, y) = to_curve(position, plot);
+                    let mut curve = self.curve.clone();
+                    if let Some(index) = curve.insert(x, y) {
+                        state.drag = Some(index);
+                        shell.publish((self.on_change)(curve));
+                    }
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                let Some(index) = cursor.position_over(bounds).and_then(|position| self.point_at(position, plot)) else {
+                    return event::Status::Ignored;
+                };
+                let mut curve = self.curve.clone();
+                curve.remove(index);
+                shell.publish((self.on_change)(curve));
+                shell.publish(self.on_release.clone());
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                if state.drag.take().is_some() {
+                    shell.publish(self.on_release.clone());
+                    event::Status::Captured
+                } else {
+                    event::Status::Ignored
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                let Some(index) = state.drag else {
+                    return event::Status::Ignored;
+                };
+                let (x, y) = to_curve(position, plot);
+                let mut curve = self.curve.clone();
+                curve.move_point(index, x, y);
+                shell.publish((self.on_change)(curve));
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if tree.state.downcast_ref::<State>().drag.is_some() {
+            return mouse::Interaction::Grabbing;
+        }
+        let bounds = layout.bounds();
+        match cursor.position_over(bounds) {
+            Some(position) if self.point_at(position, Self::plot_bounds(bounds)).is_some() => mouse::Interaction::Grab,
+            Some(_) => mouse::Interaction::Crosshair,
+            None => mouse::Interaction::Idle,
+        }
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Renderer::Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let plot = Self::plot_bounds(bounds);
+        fill(renderer, bounds, Color::from_rgb(0.16, 0.16, 0.16));
+
+        if let Some(histogram) = self.histogram {
+            let peak = histogram.iter().copied().max().unwrap_or(0);
+            draw_bars(renderer, plot, histogram, peak, Color { a: 0.35, ..self.color });
+        }
+
+        let columns = plot.width.floor() as usize;
+        let guide = Color::from_rgba(1.0, 1.0, 1.0, 0.12);
+        for quarter in [0.25, 0.5, 0.75] {
+            fill(renderer, Rectangle::new(Point::new(plot.x + plot.width * quarter, plot.y), Size::new(1.0, plot.height)), guide);
+            fill(renderer, Rectangle::new(Point::new(plot.x, plot.y + plot.height * quarter), Size::new(plot.width, 1.0)), guide);
+        }
+
+        // The curve is drawn a pixel column at a time, each spanning up or down to the previous one.
+        let line = |renderer: &mut Renderer, f: &dyn Fn(f32) -> f32, color: Color| {
+            let mut previous = plot.y + (1.0 - f(0.0)) * plot.height;
+            for column in 0..=columns {
+                let y = plot.y + (1.0 - f(column as f32 / columns.max(1) as f32)) * plot.height;
+                let top = y.min(previous) - LINE_WIDTH / 2.0;
+                let bottom = y.max(previous) + LINE_WIDTH / 2.0;
+                fill(renderer, Rectangle::new(Point::new(plot.x + column as f32 - LINE_WIDTH / 2.0, top), Size::new(LINE_WIDTH, bottom - top)), color);
+                previous = y;
+            }
+        };
+        line(renderer, &|x| x, Color::from_rgba(1.0, 1.0, 1.0, 0.25));
+        line(renderer, &|x| self.curve.evaluate(x), self.color);
+
+        for &point in self.curve.points() {
+            let center = to_screen(point, plot);
+            renderer.fill_quad(
+                Quad {
+                    bounds: Rectangle::new(
+                        Point::new(center.x - HANDLE_SIZE / 2.0, center.y - HANDLE_SIZE / 2.0),
+                        Size::new(HANDLE_SIZE, HANDLE_SIZE),
+                    ),
+                    border_radius: 0.0.into(),
+                    border_width: 1.5,
+                    border_color: self.color,
+                },
+                Color::WHITE,
+            );
+        }
+    }
+}
+
+impl<'a, Message, Renderer> From<CurveEditor<'a, Message>> for Element<'a, Message, Renderer>
+where
+    Message: Clone + 'a,
+    Renderer: renderer::Renderer + 'a,
+{
+    fn from(editor: CurveEditor<'a, Message>) -> Self {
+        Element::new(editor)
+    }
+}
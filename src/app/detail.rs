@@ -0,0 +1,109 @@
+use image::imageops;
+use image::{DynamicImage, ImageBuffer, Luma};
+use serde::{Deserialize, Serialize};
+
+/// Blur radius, in pixels, of chroma noise reduction at full strength.
+const MAX_CHROMA_RADIUS: f32 = 4.0;
+/// Blur radius, in pixels, of luminance noise reduction at full strength.
+const MAX_LUMINANCE_RADIUS: f32 = 2.0;
+/// Largest tone difference luminance noise reduction smooths over; bigger ones count as edges.
+const MAX_LUMINANCE_TOLERANCE: f32 = 0.12;
+
+type Plane = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// Unsharp mask: adds back the difference between the image and a blurred copy of it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sharpening {
+    /// Share of the difference added back, where `1.0` doubles it.
+    pub amount: f32,
+    /// Blur radius in pixels, roughly the size of the detail that is enhanced.
+    pub radius: f32,
+    /// Smallest difference, in 0–255 tones, that is sharpened; keeps smooth areas from getting grainy.
+    pub threshold: f32,
+}
+
+impl Default for Sharpening {
+    fn default() -> Self {
+        Sharpening { amount: 0.0, radius: 1.0, threshold: 0.0 }
+    }
+}
+
+impl Sharpening {
+    pub fn is_identity(&self) -> bool {
+        self.amount <= 0.0
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let mut rgba = image.into_rgba8();
+        let blurred = imageops::blur(&rgba, self.radius.max(0.1));
+        for (pixel, blurred) in rgba.pixels_mut().zip(blurred.pixels()) {
+            for (channel, &soft) in pixel.0[..3].iter_mut().zip(&blurred.0[..3]) {
+                let difference = *channel as f32 - soft as f32;
+                if difference.abs() >= self.threshold {
+                    *channel = (*channel as f32 + self.amount * difference).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        DynamicImage::ImageRgba8(rgba)
+    }
+}
+
+/// Smooths luminance noise while keeping edges, and blurs away colour blotches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseReduction {
+    /// Strength from `0.0` to `1.0`.
+    pub luminance: f32,
+    /// Strength from `0.0` to `1.0`.
+    pub chroma: f32,
+}
+
+impl NoiseReduction {
+    pub fn is_identity(&self) -> bool {
+        self.luminance <= 0.0 && self.chroma <= 0.0
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let mut rgba = image.into_rgba8();
+        let (width, height) = rgba.dimensions();
+
+        // Luma and colour differences with BT.709 weights; the differences are offset by
+        // one half since planes are kept within 0.0..=1.0.
+        let mut luma = Plane::new(width, height);
+        let mut blue = Plane::new(width, height);
+        let mut red = Plane::new(width, height);
+        for (x, y, pixel) in rgba.enumerate_pixels() {
+            let [r, g, b] = [0, 1, 2].map(|channel| pixel.0[channel] as f32 / 255.0);
+            let y_value = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            luma.put_pixel(x, y, Luma([y_value]));
+            blue.put_pixel(x, y, Luma([(b - y_value) / 1.8556 + 0.5]));
+            red.put_pixel(x, y, Luma([(r - y_value) / 1.5748 + 0.5]));
+        }
+
+        if self.chroma > 0.0 {
+            let radius = self.chroma * MAX_CHROMA_RADIUS;
+            blue = imageops::blur(&blue, radius);
+            red = imageops::blur(&red, radius);
+        }
+        if self.luminance > 0.0 {
+            let smooth = imageops::blur(&luma, 0.5 + self.luminance * MAX_LUMINANCE_RADIUS);
+            let tolerance = self.luminance * MAX_LUMINANCE_TOLERANCE;
+            for (value, smooth) in luma.pixels_mut().zip(smooth.pixels()) {
+                let difference = smooth.0[0] - value.0[0];
+                value.0[0] += difference * (-(difference / tolerance).powi(2)).exp();
+            }
+        }
+
+        for (x, y, pixel) in rgba.enumerate_pixels_mut() {
+            let y_value = luma.get_pixel(x, y).0[0];
+            let b = y_value + (blue.get_pixel(x, y).0[0] - 0.5) * 1.8556;
+            let r = y_value + (red.get_pixel(x, y).0[0] - 0.5) * 1.5748;
+            let g = (y_value - 0.2126 * r - 0.0722 * b) / 0.7152;
+            for (channel, value) in pixel.0[..3].iter_mut().zip([r, g, b]) {
+                *channel = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        DynamicImage::ImageRgba8(rgba)
+    }
+}
@@ -0,0 +1,350 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::app::adjust::Adjustments;
+use crate::app::detail::{NoiseReduction, Sharpening};
+use crate::app::exif_data::apply_orientation;
+use crate::app::file_ops::{write_store, SaveTicket};
+use crate::app::lut;
+use crate::app::photo_loader::PhotoId;
+use crate::app::tone::{Levels, ToneCurve};
+
+const EDITS_FILE: &str = "edits.json";
+
+/// A rectangle in fractions of the image it is applied to, so it holds at any resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// One step of a non-destructive edit. Operations are applied in stack order
+/// to the upright original.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum EditOp {
+    Crop(CropRect),
+    /// Clockwise rotation in quarter turns.
+    Rotate { quarter_turns: u8 },
+    /// Clockwise rotation by a small angle, zoomed in so no empty corners show.
+    Straighten { degrees: f32 },
+    /// The settings of the adjustments panel.
+    Adjust(Adjustments),
+    Curves(ToneCurve),
+    Levels(Levels),
+    Sharpen(Sharpening),
+    ReduceNoise(NoiseReduction),
+    /// An imported 3D LUT, blended with the image by `intensity` from `0.0` to `1.0`.
+    Lut { name: String, intensity: f32 },
+}
+
+impl EditOp {
+    pub fn label(&self) -> String {
+        match self {
+            EditOp::Crop(rect) => format!("Crop to {:.0}% × {:.0}%", rect.width * 100.0, rect.height * 100.0),
+            EditOp::Rotate { quarter_turns } => format!("Rotate {}°", u32::from(*quarter_turns) * 90),
+            EditOp::Straighten { degrees } => format!("Straighten {:+.1}°", degrees),
+            EditOp::Adjust(_) => String::from("Adjustments"),
+            EditOp::Curves(_) => String::from("Tone curve"),
+            EditOp::Levels(levels) => format!(
+                "Levels {:.0}–{:.0}, gamma {:.2}",
+                levels.black * 255.0,
+                levels.white * 255.0,
+                levels.gamma
+            ),
+            EditOp::Sharpen(sharpening) => format!("Sharpen {:.0}%", sharpening.amount * 100.0),
+            EditOp::ReduceNoise(_) => String::from("Noise reduction"),
+            EditOp::Lut { name, intensity } => format!("LUT {} at {:.0}%", name, intensity * 100.0),
+        }
+    }
+
+    /// Whether the operation works on individual pixels' surroundings, so its effect
+    /// depends on the resolution it is rendered at.
+    pub fn is_detail(&self) -> bool {
+        matches!(self, EditOp::Sharpen(_) | EditOp::ReduceNoise(_))
+    }
+
+    /// Whether the operation changes the framing rather than the look of the image.
+    pub fn is_framing(&self) -> bool {
+        matches!(self, EditOp::Crop(_) | EditOp::Rotate { .. } | EditOp::Straighten { .. })
+    }
+
+    /// Whether the operation leaves the image unchanged.
+    fn is_identity(&self) -> bool {
+        match self {
+            EditOp::Crop(rect) => rect.x <= 0.0 && rect.y <= 0.0 && rect.width >= 1.0 && rect.height >= 1.0,
+            EditOp::Rotate { quarter_turns } => quarter_turns % 4 == 0,
+            EditOp::Straighten { degrees } => degrees.abs() < 0.01,
+            EditOp::Adjust(adjustments) => adjustments.is_identity(),
+            EditOp::Curves(curve) => curve.is_identity(),
+            EditOp::Levels(levels) => levels.is_identity(),
+            EditOp::Sharpen(sharpening) => sharpening.is_identity(),
+            EditOp::ReduceNoise(noise_reduction) => noise_reduction.is_identity(),
+            // Kept at zero intensity, so the LUT stays chosen while its slider is dragged.
+            EditOp::Lut { .. } => false,
+        }
+    }
+
+    /// Fails only for a LUT that cannot be loaded.
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage, String> {
+        Ok(match self {
+            EditOp::Crop(rect) => crop(image, rect),
+            EditOp::Rotate { quarter_turns } => match quarter_turns % 4 {
+                1 => image.rotate90(),
+                2 => image.rotate180(),
+                3 => image.rotate270(),
+                _ => image,
+            },
+            EditOp::Straighten { degrees } => straighten(image, *degrees),
+            EditOp::Adjust(adjustments) => adjustments.apply(image),
+            EditOp::Curves(curve) => curve.apply(image),
+            EditOp::Levels(levels) => map_channels(image, |value| levels.map(value)),
+            EditOp::Sharpen(sharpening) => sharpening.apply(image),
+            EditOp::ReduceNoise(noise_reduction) => noise_reduction.apply(image),
+            EditOp::Lut { intensity, .. } if *intensity <= 0.0 => image,
+            EditOp::Lut { name, intensity } => {
+                let lut = lut::load(name).map_err(|err| format!("could not load LUT {}: {}", name, err))?;
+                lut.apply(image, *intensity)
+            }
+        })
+    }
+}
+
+/// Applies `ops` in order to `image`.
+pub fn render(image: DynamicImage, ops: &[EditOp]) -> Result<DynamicImage, String> {
+    ops.iter().try_fold(image, |image, op| op.apply(image))
+}
+
+/// Replaces the operation of the same kind as `op` in `ops`, adding it on top if there is none.
+/// An `op` that changes nothing removes the existing one instead.
+pub fn replace_op(ops: &mut Vec<EditOp>, op: EditOp) {
+    let existing = ops.iter().rposition(|existing| mem::discriminant(existing) == mem::discriminant(&op));
+    match existing {
+        Some(index) if op.is_identity() => {
+            ops.remove(index);
+        }
+        Some(index) => ops[index] = op,
+        None if !op.is_identity() => ops.push(op),
+        None => {}
+    }
+}
+
+/// Decodes the photo at `path`, turns it upright and applies its edit stack.
+pub fn load_edited(path: &Path, orientation: Option<u16>, ops: &[EditOp]) -> Result<DynamicImage, String> {
+    let image = image::open(path).map_err(|err| err.to_string())?;
+    render(apply_orientation(image, orientation), ops)
+}
+
+fn crop(image: DynamicImage, rect: &CropRect) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return image;
+    }
+    let x = ((rect.x.clamp(0.0, 1.0) * width as f32).round() as u32).min(width - 1);
+    let y = ((rect.y.clamp(0.0, 1.0) * height as f32).round() as u32).min(height - 1);
+    let crop_width = ((rect.width * width as f32).round() as u32).clamp(1, width - x);
+    let crop_height = ((rect.height * height as f32).round() as u32).clamp(1, height - y);
+    image.crop_imm(x, y, crop_width, crop_height)
+}
+
+/// Rotates `image` clockwise by `degrees` around its centre, keeping its size and
+/// zooming in just enough that the rotated photo covers the whole frame.
+fn straighten(image: DynamicImage, degrees: f32) -> DynamicImage {
+    let source = image.into_rgba8();
+    let (width, height) = source.dimensions();
+    let (w, h) = (width as f32, height as f32);
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let scale = (w / (w * cos.abs() + h * sin.abs())).min(h / (w * sin.abs() + h * cos.abs()));
+
+    let straightened = RgbaImage::from_fn(width, height, |x, y| {
+        let u = (x as f32 + 0.5 - w / 2.0) * scale;
+        let v = (y as f32 + 0.5 - h / 2.0) * scale;
+        // Rotate the output position back into the source.
+        let source_x = u * cos + v * sin + w / 2.0;
+        let source_y = -u * sin + v * cos + h / 2.0;
+        sample_bilinear(&source, source_x - 0.5, source_y - 0.5)
+    });
+    DynamicImage::ImageRgba8(straightened)
+}
+
+fn sample_bilinear(image: &RgbaImage, x: f32, y: f32) -> Rgba<u8> {
+    let max_x = image.width() - 1;
+    let max_y = image.height() - 1;
+    let x = x.clamp(0.0, max_x as f32);
+    let y = y.clamp(0.0, max_y as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(max_x), (y0 + 1).min(max_y));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let [a, b, c, d] = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| image.get_pixel(x, y).0);
+    Rgba(std::array::from_fn(|channel| {
+        let top = a[channel] as f32 * (1.0 - fx) + b[channel] as f32 * fx;
+        let bottom = c[channel] as f32 * (1.0 - fx) + d[channel] as f32 * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }))
+}
+
+/// Runs every colour channel through `f`, which maps values in `0.0..=1.0`, leaving alpha alone.
+fn map_channels(image: DynamicImage, f: impl Fn(f32) -> f32) -> DynamicImage {
+    let lut: Vec<u8> = (0..=255u8)
+        .map(|value| (f(value as f32 / 255.0).clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+
+    let mut rgba: RgbaImage = image.into_rgba8();
+    for pixel in rgba.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = lut[*channel as usize];
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Edit stacks for every edited photo, keyed by photo.
+///
+/// Stored apart from the originals, which are never modified.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EditStore {
+    photos: HashMap<PhotoId, Vec<EditOp>>,
+}
+
+impl EditStore {
+    pub fn load() -> Self {
+        edits_path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, id: &Path) -> &[EditOp] {
+        self.photos.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_edited(&self, id: &Path) -> bool {
+        self.photos.contains_key(id)
+    }
+
+    /// Replaces the whole stack of a photo, leaving out operations that change nothing.
+    pub fn set(&mut self, id: &Path, mut ops: Vec<EditOp>) {
+        ops.retain(|op| !op.is_identity());
+        self.photos.insert(id.to_path_buf(), ops);
+        self.prune(id);
+    }
+
+    /// Updates the operation of the same kind as `op` in place, see [`replace_op`].
+    pub fn replace(&mut self, id: &Path, op: EditOp) {
+        replace_op(self.photos.entry(id.to_path_buf()).or_default(), op);
+        self.prune(id);
+    }
+
+    /// Current settings of the adjustments panel for a photo.
+    pub fn adjustments(&self, id: &Path) -> Adjustments {
+        self.get(id)
+            .iter()
+            .rev()
+            .find_map(|op| match op {
+                EditOp::Adjust(adjustments) => Some(*adjustments),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn tone_curve(&self, id: &Path) -> ToneCurve {
+        self.get(id)
+            .iter()
+            .rev()
+            .find_map(|op| match op {
+                EditOp::Curves(curve) => Some(curve.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn levels(&self, id: &Path) -> Levels {
+        self.get(id)
+            .iter()
+            .rev()
+            .find_map(|op| match op {
+                EditOp::Levels(levels) => Some(*levels),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn sharpening(&self, id: &Path) -> Sharpening {
+        self.get(id)
+            .iter()
+            .rev()
+            .find_map(|op| match op {
+                EditOp::Sharpen(sharpening) => Some(*sharpening),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn noise_reduction(&self, id: &Path) -> NoiseReduction {
+        self.get(id)
+            .iter()
+            .rev()
+            .find_map(|op| match op {
+                EditOp::ReduceNoise(noise_reduction) => Some(*noise_reduction),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Name and intensity of the LUT applied to a photo, if any.
+    pub fn lut(&self, id: &Path) -> Option<(String, f32)> {
+        self.get(id).iter().rev().find_map(|op| match op {
+            EditOp::Lut { name, intensity } => Some((name.clone(), *intensity)),
+            _ => None,
+        })
+    }
+
+    pub fn remove_at(&mut self, id: &Path, index: usize) {
+        if let Some(stack) = self.photos.get_mut(id)
+            && index < stack.len()
+        {
+            stack.remove(index);
+        }
+        self.prune(id);
+    }
+
+    /// Carries the edits of a file over to its new location after a move or rename.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if let Some(stack) = self.photos.remove(from) {
+            self.photos.insert(to.to_path_buf(), stack);
+        }
+    }
+
+    /// Drops every edit of a photo, restoring the original.
+    pub fn remove(&mut self, id: &Path) {
+        self.photos.remove(id);
+    }
+
+    fn prune(&mut self, id: &Path) {
+        if self.photos.get(id).is_some_and(Vec::is_empty) {
+            self.photos.remove(id);
+        }
+    }
+}
+
+pub async fn save_edits(edits: EditStore, ticket: SaveTicket) -> Result<(), String> {
+    write_edits(&edits, ticket).map_err(|err| err.to_string())
+}
+
+fn write_edits(edits: &EditStore, ticket: SaveTicket) -> io::Result<()> {
+    let path = edits_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let contents = serde_json::to_vec_pretty(edits).map_err(io::Error::other)?;
+    write_store(&path, &contents, ticket)
+}
+
+fn edits_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("poer").join(EDITS_FILE))
+}
@@ -0,0 +1,252 @@
+use exif::{DateTime, Exif, In, Reader, Tag, Value};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Capture metadata parsed from a photo's EXIF block. Every field is optional since
+/// cameras, phones and editing tools each write a different subset of tags.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExifData {
+    pub captured_at: Option<CaptureTime>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    /// Focal length in millimetres.
+    pub focal_length: Option<f64>,
+    /// Aperture as an f-number.
+    pub aperture: Option<f64>,
+    /// Exposure time in seconds.
+    pub exposure_time: Option<f64>,
+    pub iso: Option<u32>,
+    /// EXIF orientation, 1 (upright) through 8.
+    pub orientation: Option<u16>,
+    pub gps: Option<GpsPosition>,
+}
+
+/// Wall-clock time the photo was taken, as recorded by the camera.
+///
+/// Fields are ordered from most to least significant so the derived ordering is chronological.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CaptureTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GpsPosition {
+    /// Degrees, positive north of the equator.
+    pub latitude: f64,
+    /// Degrees, positive east of Greenwich.
+    pub longitude: f64,
+    /// Metres relative to sea level.
+    pub altitude: Option<f64>,
+}
+
+/// Reads the EXIF block of `path`, returning empty data if the file has none.
+pub fn read_exif(path: &Path) -> ExifData {
+    let Ok(file) = File::open(path) else {
+        return ExifData::default();
+    };
+    match Reader::new().read_from_container(&mut BufReader::new(file)) {
+        Ok(exif) => ExifData::from_exif(&exif),
+        Err(_) => ExifData::default(),
+    }
+}
+
+impl ExifData {
+    fn from_exif(exif: &Exif) -> Self {
+        let captured_at = [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+            .into_iter()
+            .find_map(|tag| capture_time(exif, tag));
+
+        ExifData {
+            captured_at,
+            camera_make: text(exif, Tag::Make),
+            camera_model: text(exif, Tag::Model),
+            lens: text(exif, Tag::LensModel),
+            focal_length: rational(exif, Tag::FocalLength, 0),
+            aperture: rational(exif, Tag::FNumber, 0),
+            exposure_time: rational(exif, Tag::ExposureTime, 0),
+            iso: uint(exif, Tag::PhotographicSensitivity),
+            orientation: uint(exif, Tag::Orientation)
+                .and_then(|value| u16::try_from(value).ok())
+                .filter(|value| (1..=8).contains(value)),
+            gps: gps_position(exif),
+        }
+    }
+
+    /// Short human readable exposure summary, e.g. "50 mm  f/1.8  1/250 s  ISO 200".
+    pub fn exposure_summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(focal_length) = self.focal_length {
+            parts.push(format!("{:.0} mm", focal_length));
+        }
+        if let Some(aperture) = self.aperture {
+            parts.push(format!("f/{:.1}", aperture));
+        }
+        if let Some(exposure_time) = self.exposure_time.filter(|time| *time > 0.0) {
+            if exposure_time < 1.0 {
+                parts.push(format!("1/{:.0} s", 1.0 / exposure_time));
+            } else {
+                parts.push(format!("{:.1} s", exposure_time));
+            }
+        }
+        if let Some(iso) = self.iso {
+            parts.push(format!("ISO {}", iso));
+        }
+        parts.join("  ")
+    }
+
+    /// Whether the stored pixels must be turned a quarter turn to display upright,
+    /// which swaps the displayed width and height.
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(self.orientation, Some(5..=8))
+    }
+}
+
+/// Transforms decoded pixels so they appear the way the camera was held.
+pub fn apply_orientation(image: DynamicImage, orientation: Option<u16>) -> DynamicImage {
+    match orientation {
+        Some(2) => image.fliph(),
+        Some(3) => image.rotate180(),
+        Some(4) => image.flipv(),
+        Some(5) => image.rotate90().fliph(),
+        Some(6) => image.rotate90(),
+        Some(7) => image.rotate270().fliph(),
+        Some(8) => image.rotate270(),
+        _ => image,
+    }
+}
+
+impl CaptureTime {
+    /// Seconds since the Unix epoch, treating the camera's local time as UTC.
+    pub fn unix_seconds(&self) -> i64 {
+        // Days from civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let month = i64::from(self.month);
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        days * 86_400 + i64::from(self.hour) * 3_600 + i64::from(self.minute) * 60 + i64::from(self.second)
+    }
+}
+
+impl std::fmt::Display for CaptureTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+fn capture_time(exif: &Exif, tag: Tag) -> Option<CaptureTime> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    parse_capture_time(values.first()?)
+}
+
+/// Reads an EXIF date such as "2020:01:31 13:45:00", rejecting out-of-range fields.
+fn parse_capture_time(ascii: &[u8]) -> Option<CaptureTime> {
+    let DateTime { year, month, day, hour, minute, second, .. } = DateTime::from_ascii(ascii).ok()?;
+    // Cameras without a set clock write placeholders such as "0000:00:00 00:00:00".
+    let valid_date = year != 0 && (1..=12).contains(&month) && (1..=31).contains(&day);
+    // A second of 60 is a leap second.
+    let valid_time = hour <= 23 && minute <= 59 && second <= 60;
+    (valid_date && valid_time).then_some(CaptureTime { year, month, day, hour, minute, second })
+}
+
+fn text(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(values.first()?);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn uint(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
+
+fn rational(exif: &Exif, tag: Tag, index: usize) -> Option<f64> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let value = match field.value {
+        Value::Rational(ref values) => values.get(index).filter(|r| r.denom != 0)?.to_f64(),
+        Value::SRational(ref values) => values.get(index).filter(|r| r.denom != 0)?.to_f64(),
+        _ => return None,
+    };
+    value.is_finite().then_some(value)
+}
+
+fn gps_position(exif: &Exif) -> Option<GpsPosition> {
+    let latitude = gps_coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let longitude = gps_coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    let altitude = rational(exif, Tag::GPSAltitude, 0).map(|altitude| {
+        // An altitude reference of 1 means the value is below sea level.
+        if uint(exif, Tag::GPSAltitudeRef) == Some(1) { -altitude } else { altitude }
+    });
+
+    Some(GpsPosition { latitude, longitude, altitude })
+}
+
+fn gps_coordinate(exif: &Exif, tag: Tag, reference: Tag, negative: u8) -> Option<f64> {
+    let degrees = rational(exif, tag, 0)?;
+    let minutes = rational(exif, tag, 1).unwrap_or(0.0);
+    let seconds = rational(exif, tag, 2).unwrap_or(0.0);
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+
+    let is_negative = exif
+        .get_field(reference, In::PRIMARY)
+        .and_then(|field| match field.value {
+            Value::Ascii(ref values) => values.first().and_then(|v| v.first()).copied(),
+            _ => None,
+        })
+        == Some(negative);
+
+    Some(if is_negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_capture_time() {
+        let at = parse_capture_time(b"2020:01:31 13:45:60").unwrap();
+        assert_eq!((at.year, at.month, at.day), (2020, 1, 31));
+        assert_eq!((at.hour, at.minute, at.second), (13, 45, 60));
+    }
+
+    #[test]
+    fn rejects_the_unset_clock_placeholder() {
+        assert!(parse_capture_time(b"0000:00:00 00:00:00").is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_fields() {
+        for ascii in [
+            "2020:13:01 00:00:00",
+            "2020:01:32 00:00:00",
+            "2020:01:00 00:00:00",
+            "2020:01:01 24:00:00",
+            "2020:01:01 00:60:00",
+            "2020:01:01 25:61:61",
+        ] {
+            assert!(parse_capture_time(ascii.as_bytes()).is_none(), "{}", ascii);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use iced::futures::channel::mpsc::{self as async_mpsc, UnboundedSender};
+use iced::futures::{future, SinkExt, StreamExt};
+use iced::subscription::{self, Subscription};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use crate::app::edit::{load_edited, EditOp};
+use crate::app::exif_data::CaptureTime;
+use crate::app::file_ops::{unique_target, FileResult};
+use crate::app::transform::copy_exif;
+
+/// File name template used until the user picks another one.
+pub const DEFAULT_TEMPLATE: &str = "{name}";
+/// Placeholders understood in file name templates.
+pub const TEMPLATE_HELP: &str = "{name} original name, {date} capture date, {time} capture time, {n} sequence number";
+
+/// Encoding of exported files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// Same format as each source file.
+    #[default]
+    Original,
+    Jpeg,
+    Png,
+    /// Always lossless.
+    WebP,
+    Tiff,
+    Bmp,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Original,
+        ExportFormat::Jpeg,
+        ExportFormat::Png,
+        ExportFormat::WebP,
+        ExportFormat::Tiff,
+        ExportFormat::Bmp,
+    ];
+
+    /// Whether the quality setting has an effect on the export of `source`, which is
+    /// only the case when it is written as a JPEG.
+    pub fn uses_quality(self, source: &Path) -> bool {
+        self.image_format(source) == Ok(ImageFormat::Jpeg)
+    }
+
+    fn image_format(self, source: &Path) -> Result<ImageFormat, String> {
+        match self {
+            ExportFormat::Original => ImageFormat::from_path(source).map_err(|err| err.to_string()),
+            ExportFormat::Jpeg => Ok(ImageFormat::Jpeg),
+            ExportFormat::Png => Ok(ImageFormat::Png),
+            ExportFormat::WebP => Ok(ImageFormat::WebP),
+            ExportFormat::Tiff => Ok(ImageFormat::Tiff),
+            ExportFormat::Bmp => Ok(ImageFormat::Bmp),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportFormat::Original => "Original format",
+            ExportFormat::Jpeg => "JPEG",
+            ExportFormat::Png => "PNG",
+            ExportFormat::WebP => "WebP (lossless)",
+            ExportFormat::Tiff => "TIFF",
+            ExportFormat::Bmp => "BMP",
+        })
+    }
+}
+
+/// How exported photos are scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeMode {
+    #[default]
+    FullSize,
+    /// Shrinks photos so their longer side fits, keeping the aspect ratio.
+    LongEdge,
+    /// Scales photos to exactly the given width and height.
+    Exact,
+}
+
+impl ResizeMode {
+    pub const ALL: [ResizeMode; 3] = [ResizeMode::FullSize, ResizeMode::LongEdge, ResizeMode::Exact];
+}
+
+impl fmt::Display for ResizeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResizeMode::FullSize => "Full size",
+            ResizeMode::LongEdge => "Fit long edge",
+            ResizeMode::Exact => "Exact size",
+        })
+    }
+}
+
+/// Resampling filter used when resizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleFilter {
+    Nearest,
+    Bilinear,
+    Bicubic,
+    Gaussian,
+    #[default]
+    Lanczos,
+}
+
+impl ResampleFilter {
+    pub const ALL: [ResampleFilter; 5] = [
+        ResampleFilter::Nearest,
+        ResampleFilter::Bilinear,
+        ResampleFilter::Bicubic,
+        ResampleFilter::Gaussian,
+        ResampleFilter::Lanczos,
+    ];
+
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResampleFilter::Nearest => FilterType::Nearest,
+            ResampleFilter::Bilinear => FilterType::Triangle,
+            ResampleFilter::Bicubic => FilterType::CatmullRom,
+            ResampleFilter::Gaussian => FilterType::Gaussian,
+            ResampleFilter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl fmt::Display for ResampleFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResampleFilter::Nearest => "Nearest neighbour",
+            ResampleFilter::Bilinear => "Bilinear",
+            ResampleFilter::Bicubic => "Bicubic",
+            ResampleFilter::Gaussian => "Gaussian",
+            ResampleFilter::Lanczos => "Lanczos",
+        })
+    }
+}
+
+/// Everything that decides how photos are written out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    /// JPEG quality, from 1 to 100.
+    pub quality: u8,
+    pub resize: ResizeMode,
+    pub long_edge: u32,
+    pub width: u32,
+    pub height: u32,
+    pub filter: ResampleFilter,
+    pub destination: PathBuf,
+    pub template: String,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            format: ExportFormat::default(),
+            quality: 90,
+            resize: ResizeMode::default(),
+            long_edge: 2048,
+            width: 1920,
+            height: 1080,
+            filter: ResampleFilter::default(),
+            destination: PathBuf::new(),
+            template: String::from(DEFAULT_TEMPLATE),
+        }
+    }
+}
+
+/// A photo to export, with the orientation and edits to bake into the copy.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    pub path: PathBuf,
+    pub orientation: Option<u16>,
+    pub edits: Vec<EditOp>,
+    pub captured_at: Option<CaptureTime>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExportEvent {
+    /// One more photo was written, or failed to be.
+    Exported(FileResult),
+    Finished,
+}
+
+/// Name of the file written for the `index`-th of `total` exported photos.
+///
+/// Photos without a capture time get "undated" for `{date}` and `{time}`; `{n}` counts
+/// from 1 and is zero-padded to the width of `total`. Unknown placeholders are kept as is.
+pub fn file_name(settings: &ExportSettings, job: &ExportJob, index: usize, total: usize) -> String {
+    let name = job.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("photo");
+    let (date, time) = match &job.captured_at {
+        Some(at) => (
+            format!("{:04}-{:02}-{:02}", at.year, at.month, at.day),
+            format!("{:02}{:02}{:02}", at.hour, at.minute, at.second),
+        ),
+        None => (String::from("undated"), String::from("undated")),
+    };
+    let number = format!("{:0width$}", index + 1, width = total.to_string().len());
+
+    let stem = settings.template
+        .replace("{name}", name)
+        .replace("{date}", &date)
+        .replace("{time}", &time)
+        .replace("{n}", &number)
+        .replace(['/', '\\'], "_");
+    let stem = match stem.trim() {
+        "" => name,
+        stem => stem,
+    };
+
+    let extension = match settings.format {
+        ExportFormat::Original => job.path.extension().and_then(|extension| extension.to_str()),
+        format => format.image_format(&job.path).ok().and_then(|format| format.extensions_str().first().copied()),
+    };
+    match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    }
+}
+
+/// Exports `jobs` on a worker thread, reporting each photo as it is written.
+///
+/// Dropping the subscription (e.g. when the user cancels) stops the export after the
+/// photo being written.
+pub fn export(id: u64, jobs: Arc<Vec<ExportJob>>, settings: Arc<ExportSettings>) -> Subscription<ExportEvent> {
+    subscription::channel(("photo-export", id), 100, move |mut output| async move {
+        let (sender, mut receiver) = async_mpsc::unbounded();
+        thread::spawn(move || run_export(&jobs, &settings, sender));
+
+        while let Some(event) = receiver.next().await {
+            if output.send(event).await.is_err() {
+                break;
+            }
+        }
+
+        future::pending().await
+    })
+}
+
+fn run_export(jobs: &[ExportJob], settings: &ExportSettings, events: UnboundedSender<ExportEvent>) {
+    if let Err(err) = fs::create_dir_all(&settings.destination) {
+        let err = err.to_string();
+        for job in jobs {
+            if events.unbounded_send(ExportEvent::Exported((job.path.clone(), Err(err.clone())))).is_err() {
+                return;
+            }
+        }
+    } else {
+        for (index, job) in jobs.iter().enumerate() {
+            let result = export_file(job, settings, &file_name(settings, job, index, jobs.len()));
+            if events.unbounded_send(ExportEvent::Exported((job.path.clone(), result))).is_err() {
+                return;
+            }
+        }
+    }
+    let _ = events.unbounded_send(ExportEvent::Finished);
+}
+
+fn export_file(job: &ExportJob, settings: &ExportSettings, file_name: &str) -> Result<PathBuf, String> {
+    let format = settings.format.image_format(&job.path)?;
+    let target = unique_target(Path::new(file_name), &settings.destination).map_err(|err| err.to_string())?;
+
+    // Untouched photos kept in their own format are copied, which keeps their metadata.
+    if job.edits.is_empty() && settings.format == ExportFormat::Original && settings.resize == ResizeMode::FullSize {
+        fs::copy(&job.path, &target).map_err(|err| err.to_string())?;
+        return Ok(target);
+    }
+
+    let image = resize(load_edited(&job.path, job.orientation, &job.edits)?, settings);
+    let (image, output) = match format {
+        ImageFormat::Jpeg => (DynamicImage::ImageRgb8(image.to_rgb8()), ImageOutputFormat::Jpeg(settings.quality.clamp(1, 100))),
+        // These encoders only take 8-bit pixels.
+        ImageFormat::WebP | ImageFormat::Bmp if image.color().has_alpha() => {
+            (DynamicImage::ImageRgba8(image.to_rgba8()), format.into())
+        }
+        ImageFormat::WebP | ImageFormat::Bmp => (DynamicImage::ImageRgb8(image.to_rgb8()), format.into()),
+        _ => (image, format.into()),
+    };
+
+    let mut encoded = Vec::new();
+    image.write_to(&mut Cursor::new(&mut encoded), output).map_err(|err| err.to_string())?;
+    // Keeps the camera metadata of JPEGs exported as JPEG; the pixels are upright now.
+    if format == ImageFormat::Jpeg && ImageFormat::from_path(&job.path).ok() == Some(ImageFormat::Jpeg) {
+        let original = fs::read(&job.path).map_err(|err| err.to_string())?;
+        copy_exif(&original, &mut encoded);
+    }
+    if let Err(err) = fs::write(&target, encoded) {
+        let _ = fs::remove_file(&target);
+        return Err(err.to_string());
+    }
+    Ok(target)
+}
+
+fn resize(image: DynamicImage, settings: &ExportSettings) -> DynamicImage {
+    let filter = settings.filter.filter_type();
+    match settings.resize {
+        ResizeMode::FullSize => image,
+        ResizeMode::LongEdge => {
+            let (width, height) = image.dimensions();
+            let long_edge = settings.long_edge.max(1);
+            if width.max(height) <= long_edge {
+                image
+            } else {
+                image.resize(long_edge, long_edge, filter)
+            }
+        }
+        ResizeMode::Exact => image.resize_exact(settings.width.max(1), settings.height.max(1), filter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(template: &str, format: ExportFormat) -> ExportSettings {
+        ExportSettings { template: template.to_string(), format, ..ExportSettings::default() }
+    }
+
+    fn job(path: &str, captured_at: Option<CaptureTime>) -> ExportJob {
+        ExportJob { path: PathBuf::from(path), orientation: None, edits: Vec::new(), captured_at }
+    }
+
+    fn dated(path: &str) -> ExportJob {
+        job(path, Some(CaptureTime { year: 2020, month: 1, day: 31, hour: 9, minute: 5, second: 7 }))
+    }
+
+    #[test]
+    fn expands_the_placeholders() {
+        let settings = settings("{date}_{time}_{name}", ExportFormat::Original);
+        assert_eq!(file_name(&settings, &dated("/photos/IMG_1.JPG"), 0, 1), "2020-01-31_090507_IMG_1.JPG");
+    }
+
+    #[test]
+    fn pads_the_sequence_number_to_the_width_of_the_total() {
+        let settings = settings("{n}", ExportFormat::Jpeg);
+        assert_eq!(file_name(&settings, &dated("a.png"), 0, 9), "1.jpg");
+        assert_eq!(file_name(&settings, &dated("a.png"), 4, 120), "005.jpg");
+        assert_eq!(file_name(&settings, &dated("a.png"), 119, 120), "120.jpg");
+    }
+
+    #[test]
+    fn marks_photos_without_a_capture_time_as_undated() {
+        let settings = settings("{date}-{time}", ExportFormat::Png);
+        assert_eq!(file_name(&settings, &job("a.jpg", None), 0, 1), "undated-undated.png");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_replaces_path_separators() {
+        let settings = settings("{name}/{camera}\\x", ExportFormat::Original);
+        assert_eq!(file_name(&settings, &dated("a.jpg"), 0, 1), "a_{camera}_x.jpg");
+    }
+
+    #[test]
+    fn falls_back_to_the_original_name_for_a_blank_template() {
+        let settings = settings("  ", ExportFormat::WebP);
+        assert_eq!(file_name(&settings, &dated("a.jpg"), 0, 1), "a.webp");
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TRASH_DIR: &str = "trash";
+/// Extension of the file a session keeps locked next to its trash folder while it runs.
+const LOCK_EXTENSION: &str = "lock";
+
+/// Outcome of a file operation on one photo: the source path and either the
+/// resulting path or an error message.
+pub type FileResult = (PathBuf, Result<PathBuf, String>);
+
+/// Moves every file in `paths` into `destination`, renaming on name clashes.
+pub async fn move_files(paths: Vec<PathBuf>, destination: PathBuf) -> Vec<FileResult> {
+    run_for_each(paths, |path| {
+        fs::create_dir_all(&destination)?;
+        let target = unique_target(path, &destination)?;
+        move_file(path, &target)?;
+        Ok(target)
+    })
+}
+
+/// Moves every `(from, to)` pair to exactly `to`, failing rather than overwriting.
+///
+/// All or nothing: at the first failure, the files moved so far are put back and the
+/// error is returned.
+pub async fn relocate_files(moves: Vec<(PathBuf, PathBuf)>) -> Result<Vec<FileResult>, String> {
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (from, to) in moves {
+        if let Err(err) = relocate_file(&from, &to) {
+            for (back, moved_to) in moved.iter().rev() {
+                if let Err(err) = relocate_file(moved_to, back) {
+                    eprintln!("Failed to move {} back: {}", moved_to.display(), err);
+                }
+            }
+            return Err(format!("{}: {}", from.display(), err));
+        }
+        moved.push((from, to));
+    }
+    Ok(moved.into_iter().map(|(from, to)| (from, Ok(to))).collect())
+}
+
+fn relocate_file(from: &Path, to: &Path) -> io::Result<()> {
+    if to.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", to.display())));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    move_file(from, to)
+}
+
+/// Gives the file at `path` a new name in the same folder. The original extension is kept
+/// when `name` has none.
+pub async fn rename_file(path: PathBuf, name: String) -> Vec<FileResult> {
+    let name = name.trim();
+    let result = if name.is_empty() || name.contains(['/', '\\']) {
+        Err(String::from("not a valid file name"))
+    } else {
+        let mut target = path.with_file_name(name);
+        if target.extension().is_none()
+            && let Some(extension) = path.extension()
+        {
+            target.set_extension(extension);
+        }
+        relocate_file(&path, &target).map(|()| target).map_err(|err| err.to_string())
+    };
+    vec![(path, result)]
+}
+
+/// Moves every file in `paths` into the trash, from where it can be restored.
+pub async fn trash_files(paths: Vec<PathBuf>) -> Vec<FileResult> {
+    let trash = session_trash_dir();
+    run_for_each(paths, |path| {
+        let trash = trash.as_deref().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no trash folder"))?;
+        fs::create_dir_all(trash)?;
+        let target = unique_target(path, trash)?;
+        move_file(path, &target)?;
+        Ok(target)
+    })
+}
+
+/// Permanently deletes what sessions that have ended left in the trash.
+///
+/// Each session trashes files into a folder of its own and keeps a lock on a file next
+/// to it while it runs; the folders of sessions whose lock is free are deleted, so the
+/// deletions of another window that is still open can be undone.
+pub async fn empty_stale_trash() -> Vec<FileResult> {
+    let own = session_trash_dir();
+    let sessions: BTreeSet<PathBuf> = trash_dir()
+        .and_then(|trash| fs::read_dir(trash).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .map(|path| match path.extension() {
+                    Some(extension) if extension == LOCK_EXTENSION => path.with_extension(""),
+                    _ => path,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let paths = sessions
+        .into_iter()
+        .filter(|session| Some(session) != own.as_ref() && !is_running(session))
+        .collect();
+    run_for_each(paths, |path| {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else if path.exists() {
+            fs::remove_file(path)?;
+        }
+        match fs::remove_file(path.with_extension(LOCK_EXTENSION)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(path.to_path_buf()),
+        }
+    })
+}
+
+fn trash_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("poer").join(TRASH_DIR))
+}
+
+/// Folder this session trashes files into, named after the process and its start time.
+///
+/// The lock next to it is taken before the folder is first created and held until the
+/// process exits. Without it, the session has no trash.
+fn session_trash_dir() -> Option<PathBuf> {
+    static SESSION: OnceLock<Option<(PathBuf, File)>> = OnceLock::new();
+    let session = SESSION.get_or_init(|| {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let dir = trash_dir()?.join(format!("{}-{}", started, std::process::id()));
+        match lock_session(&dir) {
+            Ok(lock) => Some((dir, lock)),
+            Err(err) => {
+                eprintln!("Failed to lock the trash of this session: {}", err);
+                None
+            }
+        }
+    });
+    session.as_ref().map(|(dir, _)| dir.clone())
+}
+
+fn lock_session(dir: &Path) -> io::Result<File> {
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock = File::create(dir.with_extension(LOCK_EXTENSION))?;
+    lock.lock()?;
+    Ok(lock)
+}
+
+/// Whether the session that trashed files into `dir` still holds its lock.
+fn is_running(dir: &Path) -> bool {
+    match File::open(dir.with_extension(LOCK_EXTENSION)) {
+        Ok(lock) => lock.try_lock().is_err(),
+        // Sessions lock before creating their folder, so one without a lock has ended.
+        Err(err) => err.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+/// Permanently deletes every file in `paths`.
+pub async fn delete_files(paths: Vec<PathBuf>) -> Vec<FileResult> {
+    run_for_each(paths, |path| {
+        fs::remove_file(path)?;
+        Ok(path.to_path_buf())
+    })
+}
+
+/// Moves `from` to `to`, falling back to copy and delete when they are on different filesystems.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) if from.exists() => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn run_for_each(paths: Vec<PathBuf>, mut operation: impl FnMut(&Path) -> io::Result<PathBuf>) -> Vec<FileResult> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let result = operation(&path).map_err(|err| err.to_string());
+            (path, result)
+        })
+        .collect()
+}
+
+/// Place of a save in the order saves were requested, taken when the snapshot to save is
+/// made rather than when it is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SaveTicket(u64);
+
+impl SaveTicket {
+    pub fn take() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        SaveTicket(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Replaces the store file at `path` with `contents`, unless a save with a later ticket
+/// has already written it.
+///
+/// Saves run one at a time, so an older snapshot that gets to run last is dropped instead
+/// of overwriting a newer one.
+pub fn write_store(path: &Path, contents: &[u8], ticket: SaveTicket) -> io::Result<()> {
+    static WRITTEN: OnceLock<Mutex<HashMap<PathBuf, SaveTicket>>> = OnceLock::new();
+    let mut written = WRITTEN.get_or_init(Default::default).lock().unwrap_or_else(|err| err.into_inner());
+    if written.get(path).is_some_and(|&last| last > ticket) {
+        return Ok(());
+    }
+    atomic_write(path, contents)?;
+    written.insert(path.to_path_buf(), ticket);
+    Ok(())
+}
+
+/// Writes `contents` to a uniquely named file next to `path` and swaps it in, so a crash
+/// never leaves a truncated file.
+pub fn atomic_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_os_string();
+    temp_name.push(format!(".{}-{}.tmp", std::process::id(), NEXT_TEMP.fetch_add(1, Ordering::Relaxed)));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = fs::write(&temp_path, contents).and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Picks a path in `directory` for `source` that does not overwrite an existing file,
+/// appending " (1)", " (2)", ... to the file stem as needed.
+pub fn unique_target(source: &Path, directory: &Path) -> io::Result<PathBuf> {
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let candidate = directory.join(file_name);
+    if !candidate.exists() {
+        return Ok(candidate);
+    }
+
+    let stem = source.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    let extension = source.extension().and_then(|ext| ext.to_str());
+    (1..)
+        .map(|n| {
+            let name = match extension {
+                Some(extension) => format!("{} ({}).{}", stem, n, extension),
+                None => format!("{} ({})", stem, n),
+            };
+            directory.join(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::AlreadyExists, "no free file name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("poer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn an_older_save_does_not_replace_a_newer_one() {
+        let dir = scratch_dir("write-store");
+        let path = dir.join("store.json");
+        let older = SaveTicket::take();
+        let newer = SaveTicket::take();
+
+        write_store(&path, b"newer", newer).unwrap();
+        write_store(&path, b"older", older).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"newer");
+
+        let newest = SaveTicket::take();
+        write_store(&path, b"newest", newest).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"newest");
+
+        // Only the store itself is left behind, no temporary files.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_session_runs_while_it_holds_its_lock() {
+        let dir = scratch_dir("trash-lock");
+        let session = dir.join("1-1");
+        assert!(!is_running(&session));
+
+        let lock = lock_session(&session).unwrap();
+        assert!(is_running(&session));
+        drop(lock);
+        assert!(!is_running(&session));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use iced::event::Event;
+use iced::subscription::{self, Subscription};
+use iced::{window, Size};
+use std::ops::{Range, RangeInclusive};
+
+/// Vertical gap between grid rows.
+pub const ROW_SPACING: f32 = 16.0;
+/// Horizontal gap between cards in a row.
+pub const COLUMN_SPACING: f32 = 12.0;
+/// Padding around the grid content.
+pub const GRID_PADDING: f32 = 20.0;
+/// Range of thumbnail widths offered by the size slider.
+pub const THUMBNAIL_SIZES: RangeInclusive<f32> = 100.0..=240.0;
+/// Thumbnail width used until the user picks another one.
+pub const DEFAULT_THUMBNAIL_SIZE: f32 = 180.0;
+/// Room around the thumbnail inside a card, horizontally.
+const CARD_MARGIN: f32 = 20.0;
+/// Room below the thumbnail for the padding, file name and annotations.
+const CARD_CAPTION: f32 = 120.0;
+/// Width reserved for the vertical scrollbar.
+const SCROLLBAR_WIDTH: f32 = 10.0;
+/// Rows laid out above and below the visible ones so scrolling does not reveal gaps.
+const OVERSCAN_ROWS: usize = 2;
+
+/// Card geometry and the part of the photo grid that is currently scrolled into view.
+///
+/// The window height stands in for the height of the grid itself, which is always
+/// smaller, so the rows it covers are a superset of the visible ones.
+#[derive(Debug, Clone, Copy)]
+pub struct GridLayout {
+    pub offset: f32,
+    pub window: Size,
+    /// Width of the panel shown beside the grid, if any.
+    pub side_panel: f32,
+    pub thumbnail_size: f32,
+}
+
+impl GridLayout {
+    pub fn new(window: Size) -> Self {
+        GridLayout {
+            offset: 0.0,
+            window,
+            side_panel: 0.0,
+            thumbnail_size: DEFAULT_THUMBNAIL_SIZE,
+        }
+    }
+
+    /// Size of the thumbnail image inside a card, in a 3:2 box.
+    pub fn image_size(&self) -> Size {
+        Size::new(self.thumbnail_size, (self.thumbnail_size * 2.0 / 3.0).round())
+    }
+
+    pub fn card_size(&self) -> Size {
+        let image = self.image_size();
+        Size::new(image.width + CARD_MARGIN, image.height + CARD_CAPTION)
+    }
+
+    /// Number of cards that fit side by side next to the side panel, at least one.
+    pub fn columns(&self) -> usize {
+        let available = self.window.width - self.side_panel - 2.0 * GRID_PADDING - SCROLLBAR_WIDTH + COLUMN_SPACING;
+        ((available / (self.card_size().width + COLUMN_SPACING)) as usize).max(1)
+    }
+
+    pub fn row_count(&self, photo_count: usize) -> usize {
+        photo_count.div_ceil(self.columns())
+    }
+
+    /// Distance between the tops of two consecutive rows.
+    fn row_height(&self) -> f32 {
+        self.card_size().height + ROW_SPACING
+    }
+
+    /// Height taken up by `rows` consecutive rows and the gaps between them.
+    pub fn rows_height(&self, rows: usize) -> f32 {
+        (rows as f32 * self.row_height() - ROW_SPACING).max(0.0)
+    }
+
+    /// Total height of a grid with `row_count` rows, including padding.
+    pub fn content_height(&self, row_count: usize) -> f32 {
+        2.0 * GRID_PADDING + self.rows_height(row_count)
+    }
+
+    /// Rows worth laying out and loading thumbnails for, out of `row_count`.
+    pub fn visible_rows(&self, row_count: usize) -> Range<usize> {
+        let row_height = self.row_height();
+        let per_screen = (self.window.height / row_height).ceil() as usize + 1;
+        let first = ((self.offset - GRID_PADDING).max(0.0) / row_height) as usize;
+        // The scrollable clamps its offset when the content shrinks, so never start past the last screen.
+        let first = first.min(row_count.saturating_sub(per_screen));
+
+        let start = first.saturating_sub(OVERSCAN_ROWS);
+        let end = (first + per_screen + OVERSCAN_ROWS).min(row_count);
+        start..end
+    }
+
+    /// Records the offset a relative scroll position will land on for a grid of `row_count` rows.
+    pub fn snap_to(&mut self, relative: f32, row_count: usize) {
+        self.offset = relative * (self.content_height(row_count) - self.window.height).max(0.0);
+    }
+}
+
+/// Reports the new window size whenever the window is resized.
+pub fn window_resizes() -> Subscription<Size> {
+    subscription::events_with(|event, _status| match event {
+        Event::Window(window::Event::Resized { width, height }) => Some(Size::new(width as f32, height as f32)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1200×800 window with the default 180 px thumbnails, whose cards are 200×240
+    /// and whose rows are 256 px apart.
+    fn layout() -> GridLayout {
+        GridLayout::new(Size::new(1200.0, 800.0))
+    }
+
+    #[test]
+    fn fits_columns_to_the_width_beside_the_side_panel() {
+        let mut grid = layout();
+        assert_eq!(grid.card_size(), Size::new(200.0, 240.0));
+        assert_eq!(grid.columns(), 5);
+
+        grid.side_panel = 240.0;
+        assert_eq!(grid.columns(), 4);
+
+        grid.window.width = 100.0;
+        assert_eq!(grid.columns(), 1);
+    }
+
+    #[test]
+    fn counts_rows_and_their_height() {
+        let grid = layout();
+        assert_eq!(grid.row_count(0), 0);
+        assert_eq!(grid.row_count(5), 1);
+        assert_eq!(grid.row_count(11), 3);
+        assert_eq!(grid.rows_height(0), 0.0);
+        assert_eq!(grid.rows_height(2), 496.0);
+        assert_eq!(grid.content_height(2), 536.0);
+    }
+
+    #[test]
+    fn lays_out_the_rows_in_view_and_some_around_them() {
+        let mut grid = layout();
+        assert_eq!(grid.visible_rows(100), 0..7);
+        assert_eq!(grid.visible_rows(3), 0..3);
+
+        grid.offset = GRID_PADDING + 10.0 * 256.0;
+        assert_eq!(grid.visible_rows(100), 8..17);
+    }
+
+    #[test]
+    fn keeps_the_last_screen_when_scrolled_past_the_end() {
+        let mut grid = layout();
+        grid.offset = 1_000_000.0;
+        assert_eq!(grid.visible_rows(12), 5..12);
+    }
+
+    #[test]
+    fn snapping_to_the_end_shows_the_last_row() {
+        let mut grid = layout();
+        grid.snap_to(1.0, 100);
+        assert_eq!(grid.offset, grid.content_height(100) - 800.0);
+        assert_eq!(grid.visible_rows(100), 93..100);
+
+        grid.snap_to(0.0, 100);
+        assert_eq!(grid.offset, 0.0);
+    }
+}
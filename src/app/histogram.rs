@@ -0,0 +1,98 @@
+use image::{Rgba, RgbaImage};
+
+use crate::app::tone::CurveChannel;
+
+/// Colour that marks pixels clipped to black in the clipping overlay.
+const SHADOW_CLIPPING_COLOR: Rgba<u8> = Rgba([40, 110, 255, 255]);
+/// Colour that marks pixels clipped to white in the clipping overlay.
+const HIGHLIGHT_CLIPPING_COLOR: Rgba<u8> = Rgba([255, 40, 40, 255]);
+
+/// End of the tonal range where detail is lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clipping {
+    Shadows,
+    Highlights,
+}
+
+impl Clipping {
+    /// Whether every channel of `pixel` sits at this end of the range.
+    fn is_clipped(self, pixel: &Rgba<u8>) -> bool {
+        let limit = match self {
+            Clipping::Shadows => 0,
+            Clipping::Highlights => 255,
+        };
+        pixel.0[..3].iter().all(|&channel| channel == limit)
+    }
+
+    /// Marks the clipped pixels of `image`, leaving the rest transparent.
+    /// Returns `None` if no pixel is clipped.
+    pub fn mask(self, image: &RgbaImage) -> Option<RgbaImage> {
+        if !image.pixels().any(|pixel| self.is_clipped(pixel)) {
+            return None;
+        }
+        let color = match self {
+            Clipping::Shadows => SHADOW_CLIPPING_COLOR,
+            Clipping::Highlights => HIGHLIGHT_CLIPPING_COLOR,
+        };
+        Some(RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+            if self.is_clipped(image.get_pixel(x, y)) { color } else { Rgba([0, 0, 0, 0]) }
+        }))
+    }
+}
+
+/// Number of pixels per tone, per channel and for luminance.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256],
+    pub luma: [u32; 256],
+    pub total: u32,
+    pub shadows_clipped: u32,
+    pub highlights_clipped: u32,
+}
+
+impl Histogram {
+    pub fn of(image: &RgbaImage) -> Self {
+        let mut histogram = Histogram {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            luma: [0; 256],
+            total: 0,
+            shadows_clipped: 0,
+            highlights_clipped: 0,
+        };
+        for pixel in image.pixels() {
+            let [red, green, blue, _] = pixel.0;
+            histogram.red[red as usize] += 1;
+            histogram.green[green as usize] += 1;
+            histogram.blue[blue as usize] += 1;
+            let luma = 0.2126 * red as f32 + 0.7152 * green as f32 + 0.0722 * blue as f32;
+            histogram.luma[luma.round() as usize] += 1;
+            histogram.total += 1;
+            histogram.shadows_clipped += u32::from(Clipping::Shadows.is_clipped(pixel));
+            histogram.highlights_clipped += u32::from(Clipping::Highlights.is_clipped(pixel));
+        }
+        histogram
+    }
+
+    /// Counts for the channel a curve applies to, luminance standing in for RGB.
+    pub fn channel(&self, channel: CurveChannel) -> &[u32; 256] {
+        match channel {
+            CurveChannel::Rgb => &self.luma,
+            CurveChannel::Red => &self.red,
+            CurveChannel::Green => &self.green,
+            CurveChannel::Blue => &self.blue,
+        }
+    }
+
+    /// Share of the pixels clipped at one end, from `0.0` to `1.0`.
+    pub fn clipped(&self, clipping: Clipping) -> f32 {
+        let clipped = match clipping {
+            Clipping::Shadows => self.shadows_clipped,
+            Clipping::Highlights => self.highlights_clipped,
+        };
+        clipped as f32 / self.total.max(1) as f32
+    }
+}
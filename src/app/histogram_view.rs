@@ -0,0 +1,118 @@
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Quad};
+use iced::advanced::widget::Tree;
+use iced::advanced::Widget;
+use iced::mouse;
+use iced::{Color, Element, Length, Point, Rectangle, Size};
+
+use crate::app::histogram::Histogram;
+
+/// Height of the plot.
+const HEIGHT: f32 = 100.0;
+
+/// Draws the red, green and blue histograms of an image with its luminance on top.
+pub struct HistogramView<'a> {
+    histogram: &'a Histogram,
+}
+
+impl<'a> HistogramView<'a> {
+    pub fn new(histogram: &'a Histogram) -> Self {
+        HistogramView { histogram }
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for HistogramView<'a>
+where
+    Renderer: renderer::Renderer,
+{
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Fixed(HEIGHT)
+    }
+
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(limits.width(Length::Fill).height(HEIGHT).resolve(Size::ZERO))
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Renderer::Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        fill(renderer, bounds, Color::from_rgb(0.16, 0.16, 0.16));
+
+        let histogram = self.histogram;
+        // All series share one scale.
+        let peak = [&histogram.red, &histogram.green, &histogram.blue, &histogram.luma]
+            .iter()
+            .flat_map(|counts| counts.iter().copied())
+            .max()
+            .unwrap_or(0);
+        let series = [
+            (&histogram.red, Color::from_rgba(0.95, 0.25, 0.25, 0.45)),
+            (&histogram.green, Color::from_rgba(0.25, 0.85, 0.25, 0.45)),
+            (&histogram.blue, Color::from_rgba(0.3, 0.45, 1.0, 0.45)),
+            (&histogram.luma, Color::from_rgba(0.9, 0.9, 0.9, 0.5)),
+        ];
+
+        for (counts, color) in series {
+            draw_bars(renderer, bounds, counts, peak, color);
+        }
+    }
+}
+
+/// Fills `bounds` with a flat colour.
+pub fn fill<Renderer: renderer::Renderer>(renderer: &mut Renderer, bounds: Rectangle, color: Color) {
+    renderer.fill_quad(
+        Quad {
+            bounds,
+            border_radius: 0.0.into(),
+            border_width: 0.0,
+            border_color: Color::TRANSPARENT,
+        },
+        color,
+    );
+}
+
+/// Draws `counts` as one bar per pixel column of `bounds`, where `peak` reaches the top.
+///
+/// Square roots keep the quieter tones visible next to a dominant peak.
+pub fn draw_bars<Renderer: renderer::Renderer>(
+    renderer: &mut Renderer,
+    bounds: Rectangle,
+    counts: &[u32; 256],
+    peak: u32,
+    color: Color,
+) {
+    let peak = peak.max(1) as f32;
+    let columns = bounds.width.floor() as usize;
+    for column in 0..columns {
+        let first = column * 256 / columns;
+        let last = ((column + 1) * 256 / columns).max(first + 1).min(256);
+        let count = counts[first..last].iter().copied().max().unwrap_or(0) as f32;
+        let height = (count / peak).sqrt() * bounds.height;
+        let bar = Rectangle::new(
+            Point::new(bounds.x + column as f32, bounds.y + bounds.height - height),
+            Size::new(1.0, height),
+        );
+        fill(renderer, bar, color);
+    }
+}
+
+impl<'a, Message, Renderer> From<HistogramView<'a>> for Element<'a, Message, Renderer>
+where
+    Renderer: renderer::Renderer + 'a,
+{
+    fn from(view: HistogramView<'a>) -> Self {
+        Element::new(view)
+    }
+}
@@ -0,0 +1,301 @@
+use std::path::{Path, PathBuf};
+
+use crate::app::annotations::Annotation;
+use crate::app::edit::{EditOp, EditStore};
+use crate::app::photo_loader::PhotoId;
+use crate::app::transform::{Transform, TransformMode};
+
+/// Most steps kept; older ones can no longer be undone.
+const MAX_STEPS: usize = 100;
+
+/// A change that can be reverted and reapplied.
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// The edit stack of a photo changed.
+    Edit {
+        photo: PhotoId,
+        before: Vec<EditOp>,
+        after: Vec<EditOp>,
+    },
+    /// The edit stacks of several photos changed at once, such as when applying a preset.
+    Edits {
+        before: Vec<(PhotoId, Vec<EditOp>)>,
+        after: Vec<(PhotoId, Vec<EditOp>)>,
+    },
+    /// Tags or ratings changed, given as the annotation of each photo before and after.
+    Annotate {
+        before: Vec<(PhotoId, Option<Annotation>)>,
+        after: Vec<(PhotoId, Option<Annotation>)>,
+    },
+    /// Files were moved or renamed, as `(from, to)` pairs.
+    Move { moves: Vec<(PathBuf, PathBuf)> },
+    /// Files were moved into the trash.
+    Delete { files: Vec<TrashedFile> },
+    /// Files were rotated or flipped, and the edit stacks drawn on them reframed to match.
+    Transform {
+        transform: Transform,
+        mode: TransformMode,
+        files: Vec<PathBuf>,
+        before: Vec<(PhotoId, Vec<EditOp>)>,
+        after: Vec<(PhotoId, Vec<EditOp>)>,
+    },
+}
+
+/// A deleted photo together with the user data that was dropped along with it.
+#[derive(Debug, Clone)]
+pub struct TrashedFile {
+    pub original: PathBuf,
+    pub trashed: PathBuf,
+    pub annotation: Option<Annotation>,
+    pub edits: Vec<EditOp>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub label: String,
+    pub action: Action,
+}
+
+/// An edit that spans several messages, such as a slider drag, recorded once it is done.
+#[derive(Debug)]
+struct PendingEdit {
+    photo: PhotoId,
+    before: Vec<EditOp>,
+    label: String,
+}
+
+/// Linear undo history. Steps before `position` are applied, the ones after it were undone
+/// and can be redone until something new is recorded.
+#[derive(Debug, Default)]
+pub struct History {
+    steps: Vec<Step>,
+    position: usize,
+    /// Position after the last undo or redo and whether it was an undo, until a new
+    /// step is recorded.
+    last_step: Option<(usize, bool)>,
+    pending_edit: Option<PendingEdit>,
+}
+
+impl History {
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Number of steps currently applied.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position < self.steps.len()
+    }
+
+    /// Adds a step on top of the applied ones, discarding the undone ones.
+    ///
+    /// Returns the trashed files of steps that fell off the end of the history,
+    /// which can no longer be restored.
+    pub fn record(&mut self, label: String, action: Action) -> Vec<PathBuf> {
+        self.steps.truncate(self.position);
+        self.steps.push(Step { label, action });
+        self.last_step = None;
+
+        let excess = self.steps.len().saturating_sub(MAX_STEPS);
+        let expired = self.steps
+            .drain(..excess)
+            .flat_map(|step| match step.action {
+                Action::Delete { files } => files.into_iter().map(|file| file.trashed).collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        self.position = self.steps.len();
+        expired
+    }
+
+    /// Steps back, returning the action to revert.
+    pub fn undo(&mut self) -> Option<Action> {
+        self.position = self.position.checked_sub(1)?;
+        self.last_step = Some((self.position, true));
+        Some(self.steps[self.position].action.clone())
+    }
+
+    /// Steps forward, returning the action to reapply.
+    pub fn redo(&mut self) -> Option<Action> {
+        let step = self.steps.get(self.position)?;
+        self.position += 1;
+        self.last_step = Some((self.position, false));
+        Some(step.action.clone())
+    }
+
+    /// Takes back the last [`History::undo`] or [`History::redo`] after it could not be
+    /// carried out, so that step is next in line again. Does nothing once another step
+    /// has been recorded since.
+    pub fn cancel_step(&mut self) {
+        if let Some((position, undo)) = self.last_step.take()
+            && position == self.position
+        {
+            self.position = if undo { position + 1 } else { position - 1 };
+        }
+    }
+
+    /// Remembers the edit stack of `photo` before a change. Until the change is finished,
+    /// further calls are ignored so a whole drag becomes a single step.
+    pub fn begin_edit(&mut self, photo: &Path, edits: &EditStore, label: impl Into<String>) {
+        if self.pending_edit.is_none() {
+            self.pending_edit = Some(PendingEdit {
+                photo: photo.to_path_buf(),
+                before: edits.get(photo).to_vec(),
+                label: label.into(),
+            });
+        }
+    }
+
+    /// Whether a change started with [`History::begin_edit`] is still going on, such as a slider drag.
+    pub fn is_editing(&self) -> bool {
+        self.pending_edit.is_some()
+    }
+
+    /// Completes the change started with [`History::begin_edit`], returning the step to
+    /// record if it changed anything.
+    pub fn finish_edit(&mut self, edits: &EditStore) -> Option<(String, Action)> {
+        let PendingEdit { photo, before, label } = self.pending_edit.take()?;
+        let after = edits.get(&photo).to_vec();
+        (before != after).then_some((label, Action::Edit { photo, before, after }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotate(quarter_turns: u8) -> Vec<EditOp> {
+        vec![EditOp::Rotate { quarter_turns }]
+    }
+
+    fn edit(before: u8, after: u8) -> Action {
+        Action::Edit { photo: PathBuf::from("a.jpg"), before: rotate(before), after: rotate(after) }
+    }
+
+    fn delete(trashed: &str) -> Action {
+        Action::Delete {
+            files: vec![TrashedFile {
+                original: PathBuf::from("a.jpg"),
+                trashed: PathBuf::from(trashed),
+                annotation: None,
+                edits: Vec::new(),
+            }],
+        }
+    }
+
+    fn after_of(action: Option<Action>) -> Vec<EditOp> {
+        match action {
+            Some(Action::Edit { after, .. }) => after,
+            other => panic!("expected an edit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut history = History::default();
+        assert!(!history.can_undo() && !history.can_redo());
+        history.record(String::from("first"), edit(0, 1));
+        history.record(String::from("second"), edit(1, 2));
+
+        assert_eq!(after_of(history.undo()), rotate(2));
+        assert_eq!(after_of(history.undo()), rotate(1));
+        assert!(history.undo().is_none());
+        assert_eq!(history.position(), 0);
+
+        assert_eq!(after_of(history.redo()), rotate(1));
+        assert_eq!(history.position(), 1);
+        assert!(history.can_undo() && history.can_redo());
+    }
+
+    #[test]
+    fn recording_discards_the_undone_steps() {
+        let mut history = History::default();
+        history.record(String::from("first"), edit(0, 1));
+        history.record(String::from("second"), edit(1, 2));
+        history.undo();
+        history.record(String::from("third"), edit(1, 3));
+
+        let labels: Vec<&str> = history.steps().iter().map(|step| step.label.as_str()).collect();
+        assert_eq!(labels, ["first", "third"]);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn forgets_the_oldest_steps_and_returns_their_trashed_files() {
+        let mut history = History::default();
+        assert!(history.record(String::from("delete"), delete("trash/a.jpg")).is_empty());
+        for _ in 1..MAX_STEPS {
+            assert!(history.record(String::from("edit"), edit(0, 1)).is_empty());
+        }
+        assert_eq!(history.steps().len(), MAX_STEPS);
+
+        let expired = history.record(String::from("edit"), edit(0, 1));
+        assert_eq!(expired, [PathBuf::from("trash/a.jpg")]);
+        assert_eq!(history.steps().len(), MAX_STEPS);
+        assert_eq!(history.position(), MAX_STEPS);
+    }
+
+    #[test]
+    fn cancels_a_step_that_could_not_be_carried_out() {
+        let mut history = History::default();
+        history.record(String::from("first"), edit(0, 1));
+        history.undo();
+        history.cancel_step();
+        assert_eq!(history.position(), 1);
+
+        history.undo();
+        history.redo();
+        history.cancel_step();
+        assert_eq!(history.position(), 0);
+
+        // Once something else is recorded, the step is no longer the one to take back.
+        history.redo();
+        history.record(String::from("second"), edit(1, 2));
+        history.cancel_step();
+        assert_eq!(history.position(), 2);
+    }
+
+    #[test]
+    fn coalesces_an_edit_into_one_step() {
+        let photo = Path::new("a.jpg");
+        let mut edits = EditStore::default();
+        let mut history = History::default();
+
+        history.begin_edit(photo, &edits, "Rotate");
+        assert!(history.is_editing());
+        edits.set(photo, rotate(1));
+        history.begin_edit(photo, &edits, "Rotate again");
+        edits.set(photo, rotate(2));
+
+        let (label, action) = history.finish_edit(&edits).unwrap();
+        assert_eq!(label, "Rotate");
+        match action {
+            Action::Edit { before, after, .. } => {
+                assert!(before.is_empty());
+                assert_eq!(after, rotate(2));
+            }
+            other => panic!("expected an edit, got {:?}", other),
+        }
+        assert!(!history.is_editing());
+    }
+
+    #[test]
+    fn an_edit_that_changes_nothing_is_not_recorded() {
+        let photo = Path::new("a.jpg");
+        let mut edits = EditStore::default();
+        let mut history = History::default();
+
+        history.begin_edit(photo, &edits, "Rotate");
+        edits.set(photo, rotate(1));
+        edits.set(photo, Vec::new());
+        assert!(history.finish_edit(&edits).is_none());
+        assert!(history.finish_edit(&edits).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::app::file_ops::atomic_write;
+
+const SETTINGS_FILE: &str = "library.json";
+
+/// A folder POER scans for photos.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LibraryRoot {
+    pub path: PathBuf,
+    pub enabled: bool,
+    /// Maximum number of directory levels to descend below `path`; `None` walks the whole tree.
+    pub max_depth: Option<usize>,
+}
+
+impl LibraryRoot {
+    pub fn new(path: PathBuf) -> Self {
+        LibraryRoot {
+            path,
+            enabled: true,
+            max_depth: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LibrarySettings {
+    pub roots: Vec<LibraryRoot>,
+}
+
+impl LibrarySettings {
+    /// Loads the saved library roots, falling back to the user's Pictures folder on first run.
+    pub fn load() -> Self {
+        settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_else(|| LibrarySettings {
+                roots: dirs::picture_dir().map(LibraryRoot::new).into_iter().collect(),
+            })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = settings_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        let contents = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        atomic_write(&path, &contents)
+    }
+
+    /// Adds `path` as a new root, returning `false` if it is already part of the library.
+    pub fn add_root(&mut self, path: PathBuf) -> bool {
+        if self.roots.iter().any(|root| root.path == path) {
+            return false;
+        }
+        self.roots.push(LibraryRoot::new(path));
+        true
+    }
+
+    pub fn remove_root(&mut self, index: usize) {
+        if index < self.roots.len() {
+            self.roots.remove(index);
+        }
+    }
+
+    pub fn enabled_roots(&self) -> Vec<LibraryRoot> {
+        self.roots.iter().filter(|root| root.enabled).cloned().collect()
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("poer").join(SETTINGS_FILE))
+}
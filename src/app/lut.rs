@@ -0,0 +1,241 @@
+use image::{DynamicImage, RgbaImage};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::app::file_ops::unique_target;
+
+const LUTS_DIR: &str = "luts";
+const CUBE_EXTENSION: &str = "cube";
+/// Largest number of entries per axis accepted from a `.cube` file.
+const MAX_LUT_SIZE: usize = 256;
+
+/// Parsed LUTs by name, with the modification time of the file they were read from.
+type LoadedLuts = HashMap<String, (SystemTime, Arc<Lut>)>;
+
+/// A 3D colour lookup table, as read from a `.cube` file.
+#[derive(Debug)]
+pub struct Lut {
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    /// Output colours with red changing fastest, then green, then blue.
+    table: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    pub fn parse(text: &str) -> Result<Lut, String> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            let triple = |words: std::str::SplitWhitespace<'_>| -> Result<[f32; 3], String> {
+                let values = words.map(str::parse::<f32>).collect::<Result<Vec<_>, _>>().map_err(|err| err.to_string())?;
+                <[f32; 3]>::try_from(values).map_err(|_| format!("expected three numbers in \"{}\"", line))
+            };
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err(String::from("1D LUTs are not supported")),
+                "LUT_3D_SIZE" => {
+                    let value = words.next().and_then(|value| value.parse::<usize>().ok());
+                    size = Some(value.filter(|size| (2..=MAX_LUT_SIZE).contains(size)).ok_or("invalid LUT_3D_SIZE")?);
+                }
+                "DOMAIN_MIN" => domain_min = triple(words)?,
+                "DOMAIN_MAX" => domain_max = triple(words)?,
+                "LUT_3D_INPUT_RANGE" => {
+                    let range = words.map(str::parse::<f32>).collect::<Result<Vec<_>, _>>().map_err(|err| err.to_string())?;
+                    let [min, max] = <[f32; 2]>::try_from(range).map_err(|_| String::from("invalid LUT_3D_INPUT_RANGE"))?;
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                }
+                // Keywords this reader has no use for.
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+                _ => table.push(triple(line.split_whitespace())?),
+            }
+        }
+
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        if table.len() != size.pow(3) {
+            return Err(format!("expected {} entries, found {}", size.pow(3), table.len()));
+        }
+        let valid_range = |channel: usize| {
+            domain_min[channel].is_finite() && domain_max[channel].is_finite() && domain_max[channel] > domain_min[channel]
+        };
+        if !(0..3).all(valid_range) {
+            return Err(String::from("invalid domain"));
+        }
+        if table.iter().flatten().any(|value| !value.is_finite()) {
+            return Err(String::from("entries must be finite numbers"));
+        }
+        Ok(Lut { size, domain_min, domain_max, table })
+    }
+
+    /// Looks up `color`, interpolating between the eight surrounding entries.
+    fn sample(&self, color: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let position: [f32; 3] = std::array::from_fn(|channel| {
+            let range = self.domain_max[channel] - self.domain_min[channel];
+            ((color[channel] - self.domain_min[channel]) / range).clamp(0.0, 1.0) * last
+        });
+        let low = position.map(|value| (value.floor() as usize).min(self.size - 2));
+        let fraction: [f32; 3] = std::array::from_fn(|channel| position[channel] - low[channel] as f32);
+        let entry = |r: usize, g: usize, b: usize| self.table[r + self.size * (g + self.size * b)];
+
+        let mut result = [0.0; 3];
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let weight: f32 = (0..3)
+                .map(|channel| if offset[channel] == 1 { fraction[channel] } else { 1.0 - fraction[channel] })
+                .product();
+            let value = entry(low[0] + offset[0], low[1] + offset[1], low[2] + offset[2]);
+            for channel in 0..3 {
+                result[channel] += weight * value[channel];
+            }
+        }
+        result
+    }
+
+    /// Maps every pixel through the table, blended with the original by `intensity`.
+    pub fn apply(&self, image: DynamicImage, intensity: f32) -> DynamicImage {
+        let intensity = intensity.clamp(0.0, 1.0);
+        let mut rgba: RgbaImage = image.into_rgba8();
+        for pixel in rgba.pixels_mut() {
+            let color = [0, 1, 2].map(|channel| pixel.0[channel] as f32 / 255.0);
+            let mapped = self.sample(color);
+            for channel in 0..3 {
+                let value = color[channel] + (mapped[channel] - color[channel]) * intensity;
+                pixel.0[channel] = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        DynamicImage::ImageRgba8(rgba)
+    }
+}
+
+/// Loads the imported LUT called `name`, keeping it in memory for later renders until
+/// its file changes.
+pub fn load(name: &str) -> Result<Arc<Lut>, String> {
+    static LOADED: OnceLock<Mutex<LoadedLuts>> = OnceLock::new();
+    let loaded = LOADED.get_or_init(Default::default);
+
+    let path = luts_dir().ok_or("no data directory")?.join(format!("{}.{}", name, CUBE_EXTENSION));
+    let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).map_err(|err| err.to_string())?;
+    if let Some((loaded_modified, lut)) = loaded.lock().map_err(|err| err.to_string())?.get(name)
+        && *loaded_modified == modified
+    {
+        return Ok(Arc::clone(lut));
+    }
+
+    let lut = Arc::new(Lut::parse(&fs::read_to_string(path).map_err(|err| err.to_string())?)?);
+    loaded.lock().map_err(|err| err.to_string())?.insert(name.to_string(), (modified, Arc::clone(&lut)));
+    Ok(lut)
+}
+
+/// Names of the imported LUTs, sorted.
+pub fn installed() -> Vec<String> {
+    let mut names: Vec<String> = luts_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case(CUBE_EXTENSION)))
+                .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Checks the `.cube` file at `path` and copies it among the imported LUTs, returning
+/// the name it was imported as.
+pub async fn import(path: PathBuf) -> Result<String, String> {
+    Lut::parse(&fs::read_to_string(&path).map_err(|err| err.to_string())?)?;
+
+    let dir = luts_dir().ok_or("no data directory")?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("LUT");
+    let target = unique_target(Path::new(&format!("{}.{}", stem, CUBE_EXTENSION)), &dir).map_err(|err| err.to_string())?;
+    fs::copy(&path, &target).map_err(|err| err.to_string())?;
+    target
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(String::from)
+        .ok_or_else(|| String::from("invalid file name"))
+}
+
+fn luts_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("poer").join(LUTS_DIR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2×2×2 identity table.
+    const IDENTITY: &str = "\
+TITLE \"Identity\"
+# Red changes fastest.
+LUT_3D_SIZE 2
+
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+";
+
+    #[test]
+    fn parses_a_valid_table() {
+        let lut = Lut::parse(IDENTITY).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.table.len(), 8);
+        assert_eq!(lut.table[1], [1.0, 0.0, 0.0]);
+        assert_eq!(lut.domain_min, [0.0; 3]);
+        assert_eq!(lut.domain_max, [1.0; 3]);
+        assert_eq!(lut.sample([0.25, 0.5, 0.75]), [0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn rejects_a_wrong_entry_count() {
+        let text = IDENTITY.replace("1 1 1\n", "");
+        assert_eq!(Lut::parse(&text).unwrap_err(), "expected 8 entries, found 7");
+    }
+
+    #[test]
+    fn rejects_1d_tables() {
+        let text = IDENTITY.replace("LUT_3D_SIZE 2", "LUT_1D_SIZE 2");
+        assert_eq!(Lut::parse(&text).unwrap_err(), "1D LUTs are not supported");
+    }
+
+    #[test]
+    fn reads_the_domain() {
+        let text = IDENTITY.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2");
+        let lut = Lut::parse(&text).unwrap();
+        assert_eq!(lut.domain_max, [2.0; 3]);
+        assert_eq!(lut.sample([1.0, 1.0, 1.0]), [0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn rejects_an_empty_or_nan_domain() {
+        for domain in ["DOMAIN_MIN 0 1 0\nDOMAIN_MAX 1 1 1", "DOMAIN_MIN 0 nan 0", "DOMAIN_MAX 1 1 NaN"] {
+            let text = IDENTITY.replace("LUT_3D_SIZE 2", &format!("LUT_3D_SIZE 2\n{}", domain));
+            assert_eq!(Lut::parse(&text).unwrap_err(), "invalid domain", "{}", domain);
+        }
+    }
+}
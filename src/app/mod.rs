@@ -1,4 +1,30 @@
+pub mod adjust;
+pub mod annotations;
+#[allow(clippy::module_inception)]
 pub mod app;
+pub mod catalog;
+pub mod crop_view;
+pub mod curve_view;
+pub mod detail;
+pub mod edit;
+pub mod exif_data;
+pub mod export;
+pub mod file_ops;
+pub mod grid;
+pub mod histogram;
+pub mod histogram_view;
+pub mod history;
+pub mod library;
+pub mod lut;
 pub mod photo_card_style;
 pub mod photo_loader;
-pub mod ui_styles;
\ No newline at end of file
+pub mod photo_view;
+pub mod preset;
+pub mod scanner;
+pub mod shortcuts;
+pub mod thumbnail;
+pub mod tone;
+pub mod transform;
+pub mod ui_styles;
+pub mod viewer;
+pub mod watcher;
@@ -1,51 +1,87 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use image;
-use walkdir::WalkDir;
-use std::fs::metadata;
+use image::io::Reader as ImageReader;
+use std::fs::{metadata, Metadata};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+use crate::app::exif_data::{read_exif, ExifData};
+
+pub const SUPPORTED_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"];
+
+/// Stable identity of a photo, unaffected by filtering and sorting: its path.
+pub type PhotoId = PathBuf;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Photo {
     pub path: PathBuf,
     pub name: String,
     pub width: u32,
     pub height: u32,
     pub size: u64,
+    pub modified: Option<SystemTime>,
+    #[serde(default)]
+    pub exif: ExifData,
 }
 
-pub async fn load_photos() -> Vec<Photo> {
-    let mut photos = Vec::new();
-    if let Some(pictures_dir) = dirs::picture_dir() {
-        let mut entries = WalkDir::new(pictures_dir)
-            .into_iter()
-            .filter_map(|e| e.ok());
-        
-        while let Some(entry) = entries.next() {
-            let path = entry.path().to_path_buf();
-            if path.extension().and_then(|e| e.to_str()).map_or(false, |ext| {
-                ["jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"].contains(&ext.to_lowercase().as_str())
-            }) {
-                if let Ok(img) = image::open(&path) {
-                    let (width, height) = (img.width(), img.height());
-                    let size = match metadata(&path) {
-                        Ok(metadata) => metadata.len(),
-                        Err(_) => 0,
-                    };
-                    let name = path.file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or_default()
-                        .to_string();
-                    
-                    photos.push(Photo {
-                        path,
-                        name,
-                        width,
-                        height,
-                        size,
-                    });
-                }
-            }
-        }
+impl Photo {
+    /// Whether the file on disk still matches what was recorded when this photo was probed.
+    pub fn is_current(&self, metadata: &Metadata) -> bool {
+        self.size == metadata.len() && self.modified == metadata.modified().ok()
     }
-    photos
+
+    /// When the photo was taken, as Unix seconds: the EXIF capture time if recorded,
+    /// otherwise the file's modification time.
+    pub fn date_taken(&self) -> Option<i64> {
+        self.exif.captured_at.map(|captured_at| captured_at.unix_seconds()).or_else(|| {
+            let modified = self.modified?.duration_since(UNIX_EPOCH).ok()?;
+            i64::try_from(modified.as_secs()).ok()
+        })
+    }
+}
+
+/// Builds a [`Photo`] record for `path`, reading its EXIF block but not its pixel data.
+///
+/// The reported dimensions are those of the photo as displayed, after EXIF orientation.
+pub fn probe_photo(path: PathBuf) -> Option<Photo> {
+    let (width, height) = probe_dimensions(&path)?;
+    let (size, modified) = match metadata(&path) {
+        Ok(metadata) => (metadata.len(), metadata.modified().ok()),
+        Err(_) => (0, None),
+    };
+    let name = path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let exif = read_exif(&path);
+    let (width, height) = if exif.swaps_dimensions() { (height, width) } else { (width, height) };
+
+    Some(Photo {
+        path,
+        name,
+        width,
+        height,
+        size,
+        modified,
+        exif,
+    })
+}
+
+/// Reads the image dimensions from the file header, only decoding the whole
+/// image when the header cannot be parsed.
+fn probe_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let header = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+
+    header.or_else(|| {
+        image::open(path).ok().map(|img| (img.width(), img.height()))
+    })
 }
 
+pub fn is_supported(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|ext| {
+        SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+    })
+}
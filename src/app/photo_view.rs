@@ -0,0 +1,305 @@
+use iced::advanced::image;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Quad};
+use iced::advanced::widget::tree::{self, Tree};
+use iced::advanced::{Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::mouse;
+use iced::{Color, Element, Length, Point, Rectangle, Size, Vector};
+
+const MIN_SCALE: f32 = 0.02;
+const MAX_SCALE: f32 = 16.0;
+const SCALE_STEP: f32 = 1.15;
+/// Distance in pixels the cursor may move between press and release for a click rather than a drag.
+const CLICK_TOLERANCE: f32 = 3.0;
+
+/// How the photo is scaled inside the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zoom {
+    /// Scale the whole photo to fit the available space.
+    Fit,
+    /// Fixed scale, where `1.0` shows one image pixel per screen pixel.
+    Scale(f32),
+}
+
+/// Displays an image at a controlled zoom level and pan offset.
+///
+/// Zoom and offset are owned by the application: wheel scrolling reports a new
+/// zoom through `on_zoom` and dragging reports a new offset through `on_pan`.
+/// The offset is the displacement of the image centre from the viewport centre.
+pub struct PhotoView<'a, Message, Handle> {
+    handle: Handle,
+    image_size: Size,
+    zoom: Zoom,
+    offset: Vector,
+    overlays: Vec<Handle>,
+    marker: Option<Rectangle>,
+    on_zoom: Box<dyn Fn(Zoom, Vector) -> Message + 'a>,
+    on_pan: Box<dyn Fn(Vector) -> Message + 'a>,
+    on_pick: Option<Box<dyn Fn(f32, f32) -> Message + 'a>>,
+}
+
+impl<'a, Message, Handle> PhotoView<'a, Message, Handle> {
+    pub fn new(
+        handle: Handle,
+        image_size: Size,
+        zoom: Zoom,
+        offset: Vector,
+        on_zoom: impl Fn(Zoom, Vector) -> Message + 'a,
+        on_pan: impl Fn(Vector) -> Message + 'a,
+    ) -> Self {
+        PhotoView {
+            handle,
+            image_size,
+            zoom,
+            offset,
+            overlays: Vec::new(),
+            marker: None,
+            on_zoom: Box::new(on_zoom),
+            on_pan: Box::new(on_pan),
+            on_pick: None,
+        }
+    }
+
+    /// Images of the same size drawn on top of the photo, in order.
+    pub fn overlays(mut self, overlays: Vec<Handle>) -> Self {
+        self.overlays = overlays;
+        self
+    }
+
+    /// Region outlined on the photo, in fractions of the image size.
+    pub fn marker(mut self, marker: Option<Rectangle>) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    /// Reports clicks on the photo that are not the end of a drag, as fractions of the image size.
+    pub fn on_pick(mut self, on_pick: impl Fn(f32, f32) -> Message + 'a) -> Self {
+        self.on_pick = Some(Box::new(on_pick));
+        self
+    }
+
+    fn scale(&self, bounds: Rectangle) -> f32 {
+        match self.zoom {
+            Zoom::Fit => fit_scale(self.image_size, bounds.size()),
+            Zoom::Scale(scale) => scale,
+        }
+    }
+
+    /// Keeps the image from being dragged further than its own edges.
+    fn clamp_offset(&self, offset: Vector, scale: f32, bounds: Rectangle) -> Vector {
+        let hidden_width = ((self.image_size.width * scale - bounds.width) / 2.0).max(0.0);
+        let hidden_height = ((self.image_size.height * scale - bounds.height) / 2.0).max(0.0);
+
+        Vector::new(
+            offset.x.clamp(-hidden_width, hidden_width),
+            offset.y.clamp(-hidden_height, hidden_height),
+        )
+    }
+
+    fn image_bounds(&self, bounds: Rectangle) -> Rectangle {
+        let scale = self.scale(bounds);
+        let offset = self.clamp_offset(self.offset, scale, bounds);
+        let size = Size::new(self.image_size.width * scale, self.image_size.height * scale);
+        let center = bounds.center() + offset;
+
+        Rectangle {
+            x: center.x - size.width / 2.0,
+            y: center.y - size.height / 2.0,
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+/// Scale at which an image of `image_size` fits entirely within `available`.
+pub fn fit_scale(image_size: Size, available: Size) -> f32 {
+    if image_size.width <= 0.0 || image_size.height <= 0.0 {
+        return 1.0;
+    }
+    (available.width / image_size.width)
+        .min(available.height / image_size.height)
+        .max(MIN_SCALE)
+}
+
+#[derive(Debug, Default)]
+struct State {
+    grabbed_at: Option<Point>,
+    starting_offset: Vector,
+    /// Whether the cursor moved far enough since the press to count as a drag.
+    dragged: bool,
+}
+
+impl<'a, Message, Renderer, Handle> Widget<Message, Renderer> for PhotoView<'a, Message, Handle>
+where
+    Renderer: image::Renderer<Handle = Handle>,
+    Handle: Clone + std::hash::Hash,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Fill
+    }
+
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(limits.width(Length::Fill).height(Length::Fill).resolve(Size::ZERO))
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        let state = tree.state.downcast_mut::<State>();
+
+        match event {
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let Some(position) = cursor.position_over(bounds) else {
+                    return event::Status::Ignored;
+                };
+                let (mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. }) = delta;
+                if y == 0.0 {
+                    return event::Status::Ignored;
+                }
+
+                let scale = self.scale(bounds);
+                let new_scale = if y > 0.0 { scale * SCALE_STEP } else { scale / SCALE_STEP }
+                    .clamp(MIN_SCALE, MAX_SCALE);
+
+                // Zoom around the cursor so the point under it stays in place.
+                let cursor_offset = position - bounds.center();
+                let offset = self.clamp_offset(self.offset, scale, bounds);
+                let new_offset = cursor_offset - (cursor_offset - offset) * (new_scale / scale);
+
+                shell.publish((self.on_zoom)(
+                    Zoom::Scale(new_scale),
+                    self.clamp_offset(new_offset, new_scale, bounds),
+                ));
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(position) = cursor.position_over(bounds) else {
+                    return event::Status::Ignored;
+                };
+                state.grabbed_at = Some(position);
+                state.starting_offset = self.clamp_offset(self.offset, self.scale(bounds), bounds);
+                state.dragged = false;
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                if state.grabbed_at.take().is_none() {
+                    return event::Status::Ignored;
+                }
+                let image_bounds = self.image_bounds(bounds);
+                if let Some(on_pick) = &self.on_pick
+                    && !state.dragged
+                    && let Some(position) = cursor.position_over(image_bounds)
+                {
+                    shell.publish(on_pick(
+                        (position.x - image_bounds.x) / image_bounds.width,
+                        (position.y - image_bounds.y) / image_bounds.height,
+                    ));
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                let Some(origin) = state.grabbed_at else {
+                    return event::Status::Ignored;
+                };
+                state.dragged |= position.distance(origin) > CLICK_TOLERANCE;
+                let offset = state.starting_offset + (position - origin);
+                shell.publish((self.on_pan)(self.clamp_offset(offset, self.scale(bounds), bounds)));
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let state = tree.state.downcast_ref::<State>();
+        if state.grabbed_at.is_some() {
+            mouse::Interaction::Grabbing
+        } else if cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Grab
+        } else {
+            mouse::Interaction::Idle
+        }
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Renderer::Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let image_bounds = self.image_bounds(bounds);
+
+        renderer.with_layer(bounds, |renderer| {
+            image::Renderer::draw(renderer, self.handle.clone(), image_bounds);
+        });
+        for overlay in &self.overlays {
+            renderer.with_layer(bounds, |renderer| {
+                image::Renderer::draw(renderer, overlay.clone(), image_bounds);
+            });
+        }
+        if let Some(marker) = self.marker {
+            let marker_bounds = Rectangle {
+                x: image_bounds.x + marker.x * image_bounds.width,
+                y: image_bounds.y + marker.y * image_bounds.height,
+                width: marker.width * image_bounds.width,
+                height: marker.height * image_bounds.height,
+            };
+            renderer.with_layer(bounds, |renderer| {
+                renderer.fill_quad(
+                    Quad {
+                        bounds: marker_bounds,
+                        border_radius: 0.0.into(),
+                        border_width: 1.5,
+                        border_color: Color::WHITE,
+                    },
+                    Color::TRANSPARENT,
+                );
+            });
+        }
+    }
+}
+
+impl<'a, Message, Renderer, Handle> From<PhotoView<'a, Message, Handle>> for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: image::Renderer<Handle = Handle> + 'a,
+    Handle: Clone + std::hash::Hash + 'a,
+{
+    fn from(view: PhotoView<'a, Message, Handle>) -> Self {
+        Element::new(view)
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::app::edit::{replace_op, EditOp};
+use crate::app::file_ops::{write_store, SaveTicket};
+
+const PRESETS_FILE: &str = "presets.json";
+
+/// A named look: the edits of a photo apart from its framing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub ops: Vec<EditOp>,
+}
+
+impl Preset {
+    /// `ops` with the preset applied on top, replacing edits of the same kind.
+    pub fn apply_to(&self, ops: &[EditOp]) -> Vec<EditOp> {
+        let mut ops = ops.to_vec();
+        for op in &self.ops {
+            replace_op(&mut ops, op.clone());
+        }
+        ops
+    }
+}
+
+/// Saved presets, in the order they were created.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Presets {
+    presets: Vec<Preset>,
+}
+
+impl Presets {
+    pub fn load() -> Self {
+        presets_path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    /// Saves the look of an edit stack as `name`, replacing a preset of the same name.
+    pub fn insert(&mut self, name: &str, ops: &[EditOp]) {
+        let preset = Preset {
+            name: name.to_string(),
+            ops: ops.iter().filter(|op| !op.is_framing()).cloned().collect(),
+        };
+        match self.presets.iter_mut().find(|existing| existing.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.presets.retain(|preset| preset.name != name);
+    }
+}
+
+pub async fn save_presets(presets: Presets, ticket: SaveTicket) -> Result<(), String> {
+    write_presets(&presets, ticket).map_err(|err| err.to_string())
+}
+
+fn write_presets(presets: &Presets, ticket: SaveTicket) -> io::Result<()> {
+    let path = presets_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let contents = serde_json::to_vec_pretty(presets).map_err(io::Error::other)?;
+    write_store(&path, &contents, ticket)
+}
+
+fn presets_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("poer").join(PRESETS_FILE))
+}
@@ -0,0 +1,166 @@
+use iced::futures::channel::mpsc::{self as async_mpsc, UnboundedSender};
+use iced::futures::{future, SinkExt, StreamExt};
+use iced::subscription::{self, Subscription};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+use crate::app::library::LibraryRoot;
+use crate::app::photo_loader::{is_supported, probe_photo, Photo};
+
+const BATCH_SIZE: usize = 256;
+const FLUSH_INTERVAL: Duration = Duration::from_millis(150);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// Image files discovered by the directory walk.
+    pub seen: usize,
+    /// Files successfully probed into a [`Photo`].
+    pub indexed: usize,
+    /// Unreadable directories and files that could not be probed.
+    pub errors: usize,
+}
+
+impl ScanProgress {
+    pub fn processed(&self) -> usize {
+        self.indexed + self.errors
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ScanEvent {
+    Batch(Vec<Photo>, ScanProgress),
+    Finished,
+}
+
+enum WorkerMessage {
+    Discovered,
+    WalkError,
+    Probed(Option<Box<Photo>>),
+}
+
+/// Photos from a previous scan, keyed by path, that can be reused if their file is unchanged.
+pub type KnownPhotos = Arc<HashMap<PathBuf, Photo>>;
+
+/// Scans `roots` on a pool of worker threads, streaming discovered photos in batches.
+///
+/// Files whose size and modification time match an entry in `known` are reused
+/// without being probed again. Dropping the subscription (e.g. when the user
+/// cancels) stops the workers.
+pub fn scan(id: u64, roots: Vec<LibraryRoot>, known: KnownPhotos) -> Subscription<ScanEvent> {
+    subscription::channel(("photo-scan", id), 100, move |mut output| async move {
+        let (sender, mut receiver) = async_mpsc::unbounded();
+        thread::spawn(move || run_scan(roots, known, sender));
+
+        while let Some(event) = receiver.next().await {
+            if output.send(event).await.is_err() {
+                break;
+            }
+        }
+
+        future::pending().await
+    })
+}
+
+fn run_scan(roots: Vec<LibraryRoot>, known: KnownPhotos, events: UnboundedSender<ScanEvent>) {
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (path_sender, path_receiver) = mpsc::sync_channel::<PathBuf>(workers * 64);
+    let path_receiver = Arc::new(Mutex::new(path_receiver));
+    let (result_sender, results) = mpsc::channel();
+
+    for _ in 0..workers {
+        let paths = Arc::clone(&path_receiver);
+        let results = result_sender.clone();
+        let cancelled = Arc::clone(&cancelled);
+        let known = Arc::clone(&known);
+        thread::spawn(move || loop {
+            let next = paths.lock().ok().and_then(|paths| paths.recv().ok());
+            let Some(path) = next else { break };
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            let photo = match (known.get(&path), fs::metadata(&path)) {
+                (Some(photo), Ok(metadata)) if photo.is_current(&metadata) => Some(photo.clone()),
+                _ => probe_photo(path),
+            };
+            if results.send(WorkerMessage::Probed(photo.map(Box::new))).is_err() {
+                break;
+            }
+        });
+    }
+    drop(path_receiver);
+
+    {
+        let results = result_sender;
+        let cancelled = Arc::clone(&cancelled);
+        thread::spawn(move || walk_roots(&roots, &path_sender, &results, &cancelled));
+    }
+
+    let mut progress = ScanProgress::default();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut last_flush = Instant::now();
+
+    loop {
+        match results.recv_timeout(FLUSH_INTERVAL) {
+            Ok(WorkerMessage::Discovered) => progress.seen += 1,
+            Ok(WorkerMessage::WalkError) | Ok(WorkerMessage::Probed(None)) => progress.errors += 1,
+            Ok(WorkerMessage::Probed(Some(photo))) => {
+                progress.indexed += 1;
+                batch.push(*photo);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        if batch.len() >= BATCH_SIZE || last_flush.elapsed() >= FLUSH_INTERVAL {
+            let photos = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+            if events.unbounded_send(ScanEvent::Batch(photos, progress)).is_err() {
+                cancelled.store(true, Ordering::Relaxed);
+                return;
+            }
+            last_flush = Instant::now();
+        }
+    }
+
+    if !batch.is_empty() {
+        let _ = events.unbounded_send(ScanEvent::Batch(batch, progress));
+    }
+    let _ = events.unbounded_send(ScanEvent::Finished);
+}
+
+fn walk_roots(
+    roots: &[LibraryRoot],
+    paths: &mpsc::SyncSender<PathBuf>,
+    results: &mpsc::Sender<WorkerMessage>,
+    cancelled: &AtomicBool,
+) {
+    for root in roots.iter().filter(|root| root.enabled) {
+        let mut walker = WalkDir::new(&root.path);
+        if let Some(depth) = root.max_depth {
+            walker = walker.max_depth(depth + 1);
+        }
+
+        for entry in walker {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            let sent = match entry {
+                Ok(entry) if is_supported(entry.path()) => {
+                    results.send(WorkerMessage::Discovered).is_ok()
+                        && paths.send(entry.into_path()).is_ok()
+                }
+                Ok(_) => true,
+                Err(_) => results.send(WorkerMessage::WalkError).is_ok(),
+            };
+            if !sent {
+                return;
+            }
+        }
+    }
+}
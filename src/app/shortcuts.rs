@@ -0,0 +1,60 @@
+use iced::event::{self, Event};
+use iced::keyboard::{self, KeyCode};
+use iced::subscription::{self, Subscription};
+
+/// Keyboard commands understood by the grid and the viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Open,
+    Close,
+    SelectAll,
+    Undo,
+    Redo,
+}
+
+/// Listens for shortcut keys that were not already handled by a focused widget,
+/// so typing in the search field never moves the selection.
+pub fn shortcuts() -> Subscription<Shortcut> {
+    subscription::events_with(|event, status| {
+        if status == event::Status::Captured {
+            return None;
+        }
+        let Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers }) = event else {
+            return None;
+        };
+
+        match key_code {
+            KeyCode::Up => Some(Shortcut::Up),
+            KeyCode::Down => Some(Shortcut::Down),
+            KeyCode::Left => Some(Shortcut::Left),
+            KeyCode::Right => Some(Shortcut::Right),
+            KeyCode::Home => Some(Shortcut::Home),
+            KeyCode::End => Some(Shortcut::End),
+            KeyCode::PageUp => Some(Shortcut::PageUp),
+            KeyCode::PageDown => Some(Shortcut::PageDown),
+            KeyCode::Enter | KeyCode::NumpadEnter => Some(Shortcut::Open),
+            KeyCode::Escape => Some(Shortcut::Close),
+            KeyCode::A if modifiers.command() => Some(Shortcut::SelectAll),
+            KeyCode::Z if modifiers.command() && modifiers.shift() => Some(Shortcut::Redo),
+            KeyCode::Z if modifiers.command() => Some(Shortcut::Undo),
+            KeyCode::Y if modifiers.command() => Some(Shortcut::Redo),
+            _ => None,
+        }
+    })
+}
+
+/// Tracks the held modifier keys so clicks can extend or toggle the selection.
+pub fn modifiers() -> Subscription<keyboard::Modifiers> {
+    subscription::events_with(|event, _status| match event {
+        Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => Some(modifiers),
+        _ => None,
+    })
+}
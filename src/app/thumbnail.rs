@@ -0,0 +1,194 @@
+use iced::futures::stream;
+use iced::subscription::{self, Subscription};
+use iced::widget::image::Handle;
+use image::DynamicImage;
+use lru::LruCache;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::exif_data::apply_orientation;
+use crate::app::photo_loader::Photo;
+
+/// Longest edge, in pixels, of a generated thumbnail.
+pub const THUMBNAIL_SIZE: u32 = 240;
+/// Number of decoded thumbnails kept in memory.
+const CACHE_CAPACITY: usize = 400;
+/// Number of thumbnails generated concurrently.
+const MAX_IN_FLIGHT: usize = 8;
+/// Bump whenever thumbnail rendering changes so previously cached files are regenerated.
+const CACHE_VERSION: u8 = 2;
+/// Size the on-disk cache is pruned to, dropping the thumbnails used least recently.
+const MAX_DISK_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Identifies a thumbnail by the file it was generated from and that file's modification time,
+/// so edits on disk never show a stale preview.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ThumbnailKey {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub orientation: Option<u16>,
+}
+
+impl ThumbnailKey {
+    pub fn for_photo(photo: &Photo) -> Self {
+        ThumbnailKey {
+            path: photo.path.clone(),
+            modified: photo.modified,
+            orientation: photo.exif.orientation,
+        }
+    }
+
+    fn file_name(&self) -> String {
+        // FNV-1a keeps the file name stable across builds, unlike `DefaultHasher`.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        feed(&[CACHE_VERSION]);
+        feed(self.path.to_string_lossy().as_bytes());
+        if let Some(modified) = self.modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+            feed(&modified.as_nanos().to_le_bytes());
+        }
+        if let Some(orientation) = self.orientation {
+            feed(&orientation.to_le_bytes());
+        }
+        format!("{:016x}.jpg", hash)
+    }
+}
+
+/// Bounded in-memory cache of decoded thumbnails, backed by an on-disk cache.
+pub struct ThumbnailCache {
+    handles: LruCache<ThumbnailKey, Handle>,
+    failed: HashSet<ThumbnailKey>,
+}
+
+impl ThumbnailCache {
+    pub fn new() -> Self {
+        ThumbnailCache {
+            handles: LruCache::new(CACHE_CAPACITY),
+            failed: HashSet::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.handles.cap()
+    }
+
+    /// Looks up a loaded thumbnail without affecting eviction order.
+    pub fn peek(&self, photo: &Photo) -> Option<&Handle> {
+        self.handles.peek(&ThumbnailKey::for_photo(photo))
+    }
+
+    /// Generates the missing thumbnails for `photos` in the background, a few at a time and
+    /// in order, so the first photos in the list are previewed first.
+    pub fn subscription<'a>(
+        &self,
+        photos: impl IntoIterator<Item = &'a Photo>,
+    ) -> Subscription<(ThumbnailKey, Result<Handle, String>)> {
+        let requests = photos
+            .into_iter()
+            .take(self.capacity())
+            .map(ThumbnailKey::for_photo)
+            .filter(|key| !self.handles.contains(key) && !self.failed.contains(key))
+            .take(MAX_IN_FLIGHT)
+            .map(|key| {
+                let id = key.clone();
+                subscription::run_with_id(
+                    id,
+                    stream::once(async move {
+                        let result = load_thumbnail(key.clone()).await;
+                        (key, result)
+                    }),
+                )
+            });
+
+        Subscription::batch(requests)
+    }
+
+    pub fn finish(&mut self, key: ThumbnailKey, result: Result<Handle, String>) {
+        match result {
+            Ok(handle) => {
+                self.handles.put(key, handle);
+            }
+            Err(err) => {
+                eprintln!("Failed to create thumbnail for {}: {}", key.path.display(), err);
+                self.failed.insert(key);
+            }
+        }
+    }
+}
+
+/// Loads the thumbnail for `key` from the disk cache, generating and storing it on a miss.
+async fn load_thumbnail(key: ThumbnailKey) -> Result<Handle, String> {
+    let cache_path = thumbnail_dir().map(|dir| dir.join(key.file_name()));
+
+    if let Some(path) = cache_path.as_deref()
+        && let Ok(cached) = image::open(path)
+    {
+        // Marks the thumbnail as recently used for pruning.
+        let _ = fs::File::options().write(true).open(path).and_then(|file| file.set_modified(SystemTime::now()));
+        return Ok(to_handle(cached));
+    }
+
+    let original = image::open(&key.path).map_err(|err| err.to_string())?;
+    let thumbnail = apply_orientation(original.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE), key.orientation);
+
+    if let Some(cache_path) = cache_path
+        && let Err(err) = store_thumbnail(&cache_path, &thumbnail)
+    {
+        eprintln!("Failed to cache thumbnail for {}: {}", key.path.display(), err);
+    }
+
+    Ok(to_handle(thumbnail))
+}
+
+/// Deletes the least recently used thumbnails from the disk cache until it fits
+/// [`MAX_DISK_CACHE_BYTES`].
+pub async fn prune_disk_cache() -> Result<(), String> {
+    let Some(dir) = thumbnail_dir() else {
+        return Ok(());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.to_string()),
+    };
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+            Some((entry.path(), metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH)))
+        })
+        .collect();
+    files.sort_by_key(|&(_, _, modified)| std::cmp::Reverse(modified));
+
+    let mut total = 0;
+    for (path, size, _) in files {
+        total += size;
+        if total > MAX_DISK_CACHE_BYTES {
+            fs::remove_file(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        }
+    }
+    Ok(())
+}
+
+fn store_thumbnail(path: &Path, thumbnail: &DynamicImage) -> image::ImageResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    DynamicImage::ImageRgb8(thumbnail.to_rgb8()).save(path)
+}
+
+fn to_handle(image: DynamicImage) -> Handle {
+    let rgba = image.into_rgba8();
+    Handle::from_pixels(rgba.width(), rgba.height(), rgba.into_raw())
+}
+
+fn thumbnail_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("poer").join("thumbnails"))
+}
@@ -0,0 +1,235 @@
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Closest two control points of a curve may get horizontally.
+const MIN_POINT_GAP: f32 = 0.02;
+/// Narrowest input range levels may be squeezed to.
+const MIN_LEVELS_RANGE: f32 = 2.0 / 255.0;
+
+/// The channel a tone curve applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurveChannel {
+    #[default]
+    Rgb,
+    Red,
+    Green,
+    Blue,
+}
+
+impl CurveChannel {
+    pub const ALL: [CurveChannel; 4] = [CurveChannel::Rgb, CurveChannel::Red, CurveChannel::Green, CurveChannel::Blue];
+}
+
+impl fmt::Display for CurveChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CurveChannel::Rgb => "RGB",
+            CurveChannel::Red => "Red",
+            CurveChannel::Green => "Green",
+            CurveChannel::Blue => "Blue",
+        })
+    }
+}
+
+/// A smooth, monotone curve through control points, mapping input to output tone.
+///
+/// Points are `(input, output)` pairs in `0.0..=1.0`, sorted by input. The first and last
+/// point always sit on the left and right edge.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CurvePoints")]
+pub struct Curve {
+    points: Vec<(f32, f32)>,
+}
+
+/// A curve as stored, checked before it becomes a [`Curve`].
+#[derive(Deserialize)]
+struct CurvePoints {
+    points: Vec<(f32, f32)>,
+}
+
+impl TryFrom<CurvePoints> for Curve {
+    type Error = String;
+
+    fn try_from(CurvePoints { points }: CurvePoints) -> Result<Self, Self::Error> {
+        if points.len() < 2 {
+            return Err(String::from("a curve needs at least two points"));
+        }
+        if points.first().map(|point| point.0) != Some(0.0) || points.last().map(|point| point.0) != Some(1.0) {
+            return Err(String::from("a curve has to start at input 0 and end at input 1"));
+        }
+        if !points.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(String::from("curve points have to be sorted by input"));
+        }
+        if points.iter().any(|&(_, y)| !(0.0..=1.0).contains(&y)) {
+            return Err(String::from("curve outputs have to be within 0 to 1"));
+        }
+        Ok(Curve { points })
+    }
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve { points: vec![(0.0, 0.0), (1.0, 1.0)] }
+    }
+}
+
+impl Curve {
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.points.iter().all(|(x, y)| (x - y).abs() < 0.001)
+    }
+
+    /// Adds a point at `x`, returning its index. Points too close to a neighbour are not added.
+    pub fn insert(&mut self, x: f32, y: f32) -> Option<usize> {
+        let index = self.points.iter().position(|&(point_x, _)| point_x > x)?;
+        let previous = self.points[index.checked_sub(1)?].0;
+        if x - previous < MIN_POINT_GAP || self.points[index].0 - x < MIN_POINT_GAP {
+            return None;
+        }
+        self.points.insert(index, (x, y.clamp(0.0, 1.0)));
+        Some(index)
+    }
+
+    /// Moves a point, keeping it between its neighbours. The end points only move vertically.
+    pub fn move_point(&mut self, index: usize, x: f32, y: f32) {
+        let last = self.points.len() - 1;
+        let x = match index {
+            0 => 0.0,
+            index if index == last => 1.0,
+            index => x.clamp(self.points[index - 1].0 + MIN_POINT_GAP, self.points[index + 1].0 - MIN_POINT_GAP),
+        };
+        if let Some(point) = self.points.get_mut(index) {
+            *point = (x, y.clamp(0.0, 1.0));
+        }
+    }
+
+    /// Removes a point, unless it is one of the end points.
+    pub fn remove(&mut self, index: usize) {
+        if index > 0 && index + 1 < self.points.len() {
+            self.points.remove(index);
+        }
+    }
+
+    /// Output tone for input `x`, by monotone cubic interpolation between the points.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let points = &self.points;
+        let last = points.len() - 1;
+        if x <= points[0].0 {
+            return points[0].1;
+        }
+        if x >= points[last].0 {
+            return points[last].1;
+        }
+
+        let slopes: Vec<f32> = points.windows(2).map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0)).collect();
+        // Harmonic means of neighbouring slopes keep the curve from overshooting between points.
+        let tangent = |index: usize| match index {
+            0 => slopes[0],
+            index if index == last => slopes[last - 1],
+            index => {
+                let (before, after) = (slopes[index - 1], slopes[index]);
+                if before * after <= 0.0 { 0.0 } else { 2.0 / (1.0 / before + 1.0 / after) }
+            }
+        };
+
+        let index = points.windows(2).position(|pair| x < pair[1].0).unwrap_or(last - 1);
+        let ((x0, y0), (x1, y1)) = (points[index], points[index + 1]);
+        let width = x1 - x0;
+        let t = (x - x0) / width;
+        let (t2, t3) = (t * t, t * t * t);
+        let value = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * width * tangent(index)
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * width * tangent(index + 1);
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// A master curve applied to all channels, followed by one curve per channel.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToneCurve {
+    pub rgb: Curve,
+    pub red: Curve,
+    pub green: Curve,
+    pub blue: Curve,
+}
+
+impl ToneCurve {
+    pub fn curve(&self, channel: CurveChannel) -> &Curve {
+        match channel {
+            CurveChannel::Rgb => &self.rgb,
+            CurveChannel::Red => &self.red,
+            CurveChannel::Green => &self.green,
+            CurveChannel::Blue => &self.blue,
+        }
+    }
+
+    pub fn curve_mut(&mut self, channel: CurveChannel) -> &mut Curve {
+        match channel {
+            CurveChannel::Rgb => &mut self.rgb,
+            CurveChannel::Red => &mut self.red,
+            CurveChannel::Green => &mut self.green,
+            CurveChannel::Blue => &mut self.blue,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        CurveChannel::ALL.iter().all(|&channel| self.curve(channel).is_identity())
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let luts = [&self.red, &self.green, &self.blue].map(|curve| {
+            (0..=255u8)
+                .map(|value| (curve.evaluate(self.rgb.evaluate(value as f32 / 255.0)) * 255.0).round() as u8)
+                .collect::<Vec<_>>()
+        });
+
+        let mut rgba: RgbaImage = image.into_rgba8();
+        for pixel in rgba.pixels_mut() {
+            for (channel, lut) in pixel.0[..3].iter_mut().zip(&luts) {
+                *channel = lut[*channel as usize];
+            }
+        }
+        DynamicImage::ImageRgba8(rgba)
+    }
+}
+
+/// Input black and white points with a midtone gamma, in `0.0..=1.0` tones.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Levels {
+    pub black: f32,
+    pub white: f32,
+    pub gamma: f32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels { black: 0.0, white: 1.0, gamma: 1.0 }
+    }
+}
+
+impl Levels {
+    pub fn is_identity(&self) -> bool {
+        self.black <= 0.0 && self.white >= 1.0 && (self.gamma - 1.0).abs() < 0.001
+    }
+
+    /// Moves the black point, keeping it below the white point.
+    pub fn with_black(self, black: f32) -> Self {
+        Levels { black: black.clamp(0.0, self.white - MIN_LEVELS_RANGE), ..self }
+    }
+
+    /// Moves the white point, keeping it above the black point.
+    pub fn with_white(self, white: f32) -> Self {
+        Levels { white: white.clamp(self.black + MIN_LEVELS_RANGE, 1.0), ..self }
+    }
+
+    /// Output tone for input `value`.
+    pub fn map(&self, value: f32) -> f32 {
+        ((value - self.black) / (self.white - self.black)).clamp(0.0, 1.0).powf(1.0 / self.gamma)
+    }
+}
@@ -0,0 +1,647 @@
+use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+use std::fs;
+use std::io::Cursor;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::app::edit::{CropRect, EditOp};
+use crate::app::exif_data::{apply_orientation, read_exif};
+use crate::app::file_ops::FileResult;
+
+/// Quality used when a rotated JPEG has to be re-encoded.
+const JPEG_QUALITY: u8 = 95;
+/// EXIF tag number of the orientation field.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// A quarter-turn rotation or mirror applied to the photo file itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    RotateLeft,
+    RotateRight,
+    FlipHorizontal,
+    FlipVertical,
+}
+
+/// How a transform is written to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformMode {
+    /// Rewrite only the EXIF orientation of JPEGs; other formats are left alone.
+    Lossless,
+    /// Decode, transform and re-encode the pixels of every file.
+    Reencode,
+}
+
+impl Transform {
+    /// How the transform is named in the history panel.
+    pub fn label(self) -> &'static str {
+        match self {
+            Transform::RotateLeft => "Rotate left",
+            Transform::RotateRight => "Rotate right",
+            Transform::FlipHorizontal => "Flip horizontal",
+            Transform::FlipVertical => "Flip vertical",
+        }
+    }
+
+    /// The transform that undoes `self`.
+    pub fn inverse(self) -> Transform {
+        match self {
+            Transform::RotateLeft => Transform::RotateRight,
+            Transform::RotateRight => Transform::RotateLeft,
+            flip => flip,
+        }
+    }
+
+    /// The EXIF orientation that shows a photo with `orientation` transformed by `self`.
+    ///
+    /// An orientation is a clockwise rotation followed by an optional horizontal flip,
+    /// see [`apply_orientation`].
+    pub fn apply_to_orientation(self, orientation: u16) -> u16 {
+        let (turns, flipped) = match orientation {
+            2 => (0, true),
+            3 => (2, false),
+            4 => (2, true),
+            5 => (1, true),
+            6 => (1, false),
+            7 => (3, true),
+            8 => (3, false),
+            _ => (0, false),
+        };
+        // A rotation after a flip turns the other way round.
+        let (turns, flipped) = match self {
+            Transform::RotateRight => (turns + if flipped { 3 } else { 1 }, flipped),
+            Transform::RotateLeft => (turns + if flipped { 1 } else { 3 }, flipped),
+            Transform::FlipHorizontal => (turns, !flipped),
+            Transform::FlipVertical => (turns + 2, !flipped),
+        };
+        match (turns % 4, flipped) {
+            (0, false) => 1,
+            (0, true) => 2,
+            (2, false) => 3,
+            (2, true) => 4,
+            (1, true) => 5,
+            (1, false) => 6,
+            (3, true) => 7,
+            _ => 8,
+        }
+    }
+
+    /// An edit stack that frames the transformed file the way `ops` framed the original.
+    ///
+    /// Crops follow the photo round, and mirroring a photo also mirrors the direction
+    /// of its rotations.
+    pub fn apply_to_edits(self, ops: &[EditOp]) -> Vec<EditOp> {
+        let flips = matches!(self, Transform::FlipHorizontal | Transform::FlipVertical);
+        ops.iter()
+            .map(|op| match op {
+                EditOp::Crop(rect) => EditOp::Crop(self.apply_to_rect(rect)),
+                EditOp::Rotate { quarter_turns } if flips => EditOp::Rotate { quarter_turns: (4 - quarter_turns % 4) % 4 },
+                EditOp::Straighten { degrees } if flips => EditOp::Straighten { degrees: -degrees },
+                op => op.clone(),
+            })
+            .collect()
+    }
+
+    fn apply_to_rect(self, rect: &CropRect) -> CropRect {
+        match self {
+            Transform::RotateRight => CropRect { x: 1.0 - rect.y - rect.height, y: rect.x, width: rect.height, height: rect.width },
+            Transform::RotateLeft => CropRect { x: rect.y, y: 1.0 - rect.x - rect.width, width: rect.height, height: rect.width },
+            Transform::FlipHorizontal => CropRect { x: 1.0 - rect.x - rect.width, ..*rect },
+            Transform::FlipVertical => CropRect { y: 1.0 - rect.y - rect.height, ..*rect },
+        }
+    }
+
+    fn apply(self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Transform::RotateLeft => image.rotate270(),
+            Transform::RotateRight => image.rotate90(),
+            Transform::FlipHorizontal => image.fliph(),
+            Transform::FlipVertical => image.flipv(),
+        }
+    }
+}
+
+/// Applies `transform` to every file in `paths`.
+pub async fn transform_files(paths: Vec<PathBuf>, transform: Transform, mode: TransformMode) -> Vec<FileResult> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let result = transform_file(&path, transform, mode).map(|()| path.clone());
+            (path, result)
+        })
+        .collect()
+}
+
+/// Applies `transform` to every file in `paths`, all or nothing: at the first failure,
+/// the files transformed so far are turned back and the error is returned.
+pub async fn transform_all(paths: Vec<PathBuf>, transform: Transform, mode: TransformMode) -> Result<Vec<FileResult>, String> {
+    for (index, path) in paths.iter().enumerate() {
+        if let Err(err) = transform_file(path, transform, mode) {
+            for done in paths[..index].iter().rev() {
+                if let Err(err) = transform_file(done, transform.inverse(), mode) {
+                    eprintln!("Failed to turn {} back: {}", done.display(), err);
+                }
+            }
+            return Err(format!("{}: {}", path.display(), err));
+        }
+    }
+    Ok(paths.into_iter().map(|path| (path.clone(), Ok(path))).collect())
+}
+
+fn transform_file(path: &Path, transform: Transform, mode: TransformMode) -> Result<(), String> {
+    let format = ImageFormat::from_path(path).map_err(|err| err.to_string())?;
+    let original = fs::read(path).map_err(|err| err.to_string())?;
+    // Read from the file rather than the catalog, which may not have caught up with an
+    // earlier transform yet.
+    let orientation = read_exif(path).orientation;
+
+    let contents = if mode == TransformMode::Lossless {
+        // Re-encoding could drop animation frames, metadata or quality.
+        if format != ImageFormat::Jpeg {
+            return Err(String::from("only JPEGs can be rotated losslessly, turn off lossless to re-encode"));
+        }
+        let mut contents = original;
+        set_jpeg_orientation(&mut contents, transform.apply_to_orientation(orientation.unwrap_or(1)))?;
+        contents
+    } else {
+        let image = image::load_from_memory_with_format(&original, format).map_err(|err| err.to_string())?;
+        let image = transform.apply(apply_orientation(image, orientation));
+        let output_format = match format {
+            ImageFormat::Jpeg => ImageOutputFormat::Jpeg(JPEG_QUALITY),
+            format => ImageOutputFormat::from(format),
+        };
+        let mut encoded = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut encoded), output_format)
+            .map_err(|err| err.to_string())?;
+        if format == ImageFormat::Jpeg {
+            // Keep the camera metadata; the pixels are upright now.
+            copy_exif(&original, &mut encoded);
+        }
+        encoded
+    };
+
+    let temp_path = path.with_extension("poer.tmp");
+    fs::write(&temp_path, contents).map_err(|err| err.to_string())?;
+    fs::rename(&temp_path, path).map_err(|err| err.to_string())
+}
+
+/// Copies the EXIF segment of the JPEG `source` into the freshly encoded JPEG `encoded`,
+/// with its orientation reset since encoded pixels are always upright.
+pub fn copy_exif(source: &[u8], encoded: &mut Vec<u8>) {
+    if let Some(mut exif) = exif_segment(source).map(|range| source[range].to_vec()) {
+        set_segment_orientation(&mut exif, 1);
+        let position = exif_position(encoded);
+        encoded.splice(position..position, exif);
+    }
+}
+
+/// Byte ranges of the JPEG segments before the image data, as `(marker, start, end)`
+/// where `start` is the position of the 0xFF byte.
+fn jpeg_segments(contents: &[u8]) -> Vec<(u8, usize, usize)> {
+    let mut segments = Vec::new();
+    if !contents.starts_with(&[0xFF, 0xD8]) {
+        return segments;
+    }
+    let mut position = 2;
+    while position + 4 <= contents.len() && contents[position] == 0xFF {
+        let marker = contents[position + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([contents[position + 2], contents[position + 3]]) as usize;
+        if length < 2 {
+            break;
+        }
+        let end = (position + 2 + length).min(contents.len());
+        segments.push((marker, position, end));
+        position = end;
+    }
+    segments
+}
+
+/// Byte range of the whole APP1 segment holding the EXIF data, marker included.
+fn exif_segment(contents: &[u8]) -> Option<Range<usize>> {
+    jpeg_segments(contents)
+        .into_iter()
+        .find(|&(marker, start, end)| marker == 0xE1 && contents[start + 4..end].starts_with(b"Exif\0\0"))
+        .map(|(_, start, end)| start..end)
+}
+
+/// Where an EXIF segment goes in a JPEG without one: right after the JFIF segment if
+/// there is one, since that has to come first, or else right after the start marker.
+fn exif_position(contents: &[u8]) -> usize {
+    match jpeg_segments(contents).first() {
+        Some(&(0xE0, _, end)) => end,
+        _ => 2,
+    }
+}
+
+/// Rewrites the orientation tag of a JPEG in place, adding an EXIF segment if it has none.
+fn set_jpeg_orientation(contents: &mut Vec<u8>, orientation: u16) -> Result<(), String> {
+    if !contents.starts_with(&[0xFF, 0xD8]) {
+        return Err(String::from("not a JPEG file"));
+    }
+    match exif_segment(contents) {
+        Some(range) => {
+            if set_segment_orientation(&mut contents[range.clone()], orientation) {
+                return Ok(());
+            }
+            let mut segment = contents[range.clone()].to_vec();
+            if !add_segment_orientation(&mut segment, orientation) {
+                return Err(String::from("could not add an orientation to the EXIF data, re-encode instead"));
+            }
+            contents.splice(range, segment);
+            Ok(())
+        }
+        None => {
+            let position = exif_position(contents);
+            contents.splice(position..position, orientation_segment(orientation));
+            Ok(())
+        }
+    }
+}
+
+/// Sets the orientation in an EXIF APP1 segment, returning whether the tag was found.
+fn set_segment_orientation(segment: &mut [u8], orientation: u16) -> bool {
+    let Some(tiff) = Tiff::new(segment) else {
+        return false;
+    };
+    let Some(entry) = tiff.entry(ORIENTATION_TAG) else {
+        return false;
+    };
+    let value = tiff.encode_u16(orientation);
+    let start = Tiff::START + entry + 8;
+    segment[start..start + 2].copy_from_slice(&value);
+    true
+}
+
+/// Adds an orientation tag to an EXIF APP1 segment that has none, returning whether it could.
+///
+/// The first IFD is copied to the end of the segment with the tag added, so the offsets
+/// of all other data stay valid; the old copy is left unused.
+fn add_segment_orientation(segment: &mut Vec<u8>, orientation: u16) -> bool {
+    let Some(tiff) = Tiff::new(segment) else {
+        return false;
+    };
+    let Some((ifd, entries)) = tiff.first_ifd() else {
+        return false;
+    };
+    let entries_end = ifd + 2 + entries.len();
+    let Some(next_ifd) = tiff.bytes.get(entries_end..entries_end + 4) else {
+        return false;
+    };
+    let Ok(count) = u16::try_from(entries.len() / 12 + 1) else {
+        return false;
+    };
+
+    let mut orientation_entry = Vec::with_capacity(12);
+    orientation_entry.extend_from_slice(&tiff.encode_u16(ORIENTATION_TAG));
+    orientation_entry.extend_from_slice(&tiff.encode_u16(3));
+    orientation_entry.extend_from_slice(&tiff.encode_u32(1));
+    orientation_entry.extend_from_slice(&tiff.encode_u16(orientation));
+    orientation_entry.extend_from_slice(&[0, 0]);
+
+    // Entries are sorted by tag.
+    let mut new_ifd = tiff.encode_u16(count).to_vec();
+    let mut added = false;
+    for entry in entries.chunks_exact(12) {
+        if !added && tiff.decode_u16([entry[0], entry[1]]) > ORIENTATION_TAG {
+            new_ifd.extend_from_slice(&orientation_entry);
+            added = true;
+        }
+        new_ifd.extend_from_slice(entry);
+    }
+    if !added {
+        new_ifd.extend_from_slice(&orientation_entry);
+    }
+    new_ifd.extend_from_slice(next_ifd);
+
+    // IFDs start on a word boundary.
+    let padding = tiff.bytes.len() % 2;
+    let new_offset = tiff.bytes.len() + padding;
+    let length = segment.len() - 2 + padding + new_ifd.len();
+    let (Ok(length), Ok(new_offset)) = (u16::try_from(length), u32::try_from(new_offset)) else {
+        return false;
+    };
+    let new_offset = tiff.encode_u32(new_offset);
+    segment.extend(std::iter::repeat_n(0, padding));
+    segment.extend_from_slice(&new_ifd);
+    segment[Tiff::START + 4..Tiff::START + 8].copy_from_slice(&new_offset);
+    segment[2..4].copy_from_slice(&length.to_be_bytes());
+    true
+}
+
+/// The TIFF data inside an EXIF APP1 segment, read in the byte order it declares.
+struct Tiff<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    /// Where the TIFF data starts in the segment, after the marker, length and "Exif\0\0".
+    const START: usize = 10;
+
+    fn new(segment: &'a [u8]) -> Option<Self> {
+        let bytes = segment.get(Self::START..)?;
+        let big_endian = match bytes.get(..2)? {
+            b"MM" => true,
+            b"II" => false,
+            _ => return None,
+        };
+        Some(Tiff { bytes, big_endian })
+    }
+
+    /// Offset of the first IFD and its entries, which must all lie within the data.
+    fn first_ifd(&self) -> Option<(usize, &'a [u8])> {
+        let ifd = self.u32_at(4)? as usize;
+        let count = self.u16_at(ifd)? as usize;
+        let start = ifd.checked_add(2)?;
+        let entries = self.bytes.get(start..start.checked_add(count * 12)?)?;
+        Some((ifd, entries))
+    }
+
+    /// Offset of the entry for `tag` in the first IFD, if it has one.
+    fn entry(&self, tag: u16) -> Option<usize> {
+        let (ifd, entries) = self.first_ifd()?;
+        entries
+            .chunks_exact(12)
+            .position(|entry| self.decode_u16([entry[0], entry[1]]) == tag)
+            .map(|index| ifd + 2 + index * 12)
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes.get(offset..offset.checked_add(2)?)?;
+        Some(self.decode_u16([bytes[0], bytes[1]]))
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes.get(offset..offset.checked_add(4)?)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn decode_u16(&self, bytes: [u8; 2]) -> u16 {
+        if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    }
+
+    fn encode_u16(&self, value: u16) -> [u8; 2] {
+        if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    }
+
+    fn encode_u32(&self, value: u32) -> [u8; 4] {
+        if self.big_endian { value.to_be_bytes() } else { value.to_le_bytes() }
+    }
+}
+
+/// A minimal EXIF APP1 segment holding only an orientation.
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"MM\0\x2A");
+    tiff.extend_from_slice(&8u32.to_be_bytes());
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend_from_slice(&ORIENTATION_TAG.to_be_bytes());
+    // One SHORT value, padded to the four byte value field.
+    tiff.extend_from_slice(&3u16.to_be_bytes());
+    tiff.extend_from_slice(&1u32.to_be_bytes());
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    segment.extend_from_slice(b"Exif\0\0");
+    segment.extend_from_slice(&tiff);
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::edit::render;
+    use image::{Rgba, RgbaImage};
+
+    const ALL: [Transform; 4] = [Transform::RotateLeft, Transform::RotateRight, Transform::FlipHorizontal, Transform::FlipVertical];
+    const IMAGE_WIDTH_TAG: u16 = 0x0100;
+    const RESOLUTION_UNIT_TAG: u16 = 0x0128;
+    /// A JFIF APP0 segment, which has to come before any other.
+    const JFIF: [u8; 18] = [0xFF, 0xE0, 0, 16, b'J', b'F', b'I', b'F', 0, 1, 1, 0, 0, 1, 0, 1, 0, 0];
+
+    /// An EXIF APP1 segment in the given byte order whose first IFD holds `tags`, each a
+    /// single SHORT value.
+    fn exif_app1(big_endian: bool, tags: &[(u16, u16)]) -> Vec<u8> {
+        let u16_bytes = |value: u16| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let u32_bytes = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let mut tiff = if big_endian { b"MM".to_vec() } else { b"II".to_vec() };
+        tiff.extend(u16_bytes(42));
+        tiff.extend(u32_bytes(8));
+        tiff.extend(u16_bytes(tags.len() as u16));
+        for &(tag, value) in tags {
+            tiff.extend(u16_bytes(tag));
+            tiff.extend(u16_bytes(3));
+            tiff.extend(u32_bytes(1));
+            tiff.extend(u16_bytes(value));
+            tiff.extend([0, 0]);
+        }
+        tiff.extend(u32_bytes(0));
+
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend(((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        segment.extend(b"Exif\0\0");
+        segment.extend(tiff);
+        segment
+    }
+
+    /// A JPEG holding `segments` and an empty scan.
+    fn jpeg(segments: &[&[u8]]) -> Vec<u8> {
+        let mut contents = vec![0xFF, 0xD8];
+        for segment in segments {
+            contents.extend_from_slice(segment);
+        }
+        contents.extend([0xFF, 0xDA, 0, 2, 0xFF, 0xD9]);
+        contents
+    }
+
+    /// Reads a tag back with the EXIF library rather than the code under test.
+    fn read_tag(contents: &[u8], tag: exif::Tag) -> Option<u32> {
+        let exif = exif::Reader::new().read_from_container(&mut Cursor::new(contents)).ok()?;
+        exif.get_field(tag, exif::In::PRIMARY)?.value.get_uint(0)
+    }
+
+    #[test]
+    fn rewrites_an_existing_orientation() {
+        for big_endian in [false, true] {
+            let segment = exif_app1(big_endian, &[(IMAGE_WIDTH_TAG, 640), (ORIENTATION_TAG, 1), (RESOLUTION_UNIT_TAG, 2)]);
+            let mut contents = jpeg(&[&segment]);
+            let length = contents.len();
+
+            set_jpeg_orientation(&mut contents, 6).unwrap();
+            assert_eq!(contents.len(), length);
+            assert_eq!(read_tag(&contents, exif::Tag::Orientation), Some(6), "big endian: {}", big_endian);
+            assert_eq!(read_tag(&contents, exif::Tag::ImageWidth), Some(640));
+        }
+    }
+
+    #[test]
+    fn adds_a_missing_orientation() {
+        for big_endian in [false, true] {
+            let segment = exif_app1(big_endian, &[(IMAGE_WIDTH_TAG, 640), (RESOLUTION_UNIT_TAG, 2)]);
+            let mut contents = jpeg(&[&segment]);
+
+            set_jpeg_orientation(&mut contents, 8).unwrap();
+            assert_eq!(read_tag(&contents, exif::Tag::Orientation), Some(8), "big endian: {}", big_endian);
+            assert_eq!(read_tag(&contents, exif::Tag::ImageWidth), Some(640));
+            assert_eq!(read_tag(&contents, exif::Tag::ResolutionUnit), Some(2));
+
+            // Entries stay sorted by tag.
+            let range = exif_segment(&contents).unwrap();
+            let tiff = Tiff::new(&contents[range]).unwrap();
+            let (_, entries) = tiff.first_ifd().unwrap();
+            let tags: Vec<u16> = entries.chunks_exact(12).map(|entry| tiff.decode_u16([entry[0], entry[1]])).collect();
+            assert_eq!(tags, [IMAGE_WIDTH_TAG, ORIENTATION_TAG, RESOLUTION_UNIT_TAG]);
+        }
+    }
+
+    #[test]
+    fn adds_an_exif_segment_after_jfif() {
+        let mut contents = jpeg(&[&JFIF]);
+        set_jpeg_orientation(&mut contents, 3).unwrap();
+
+        let markers: Vec<u8> = jpeg_segments(&contents).iter().map(|&(marker, _, _)| marker).collect();
+        assert_eq!(markers, [0xE0, 0xE1]);
+        assert_eq!(read_tag(&contents, exif::Tag::Orientation), Some(3));
+    }
+
+    #[test]
+    fn finds_exif_after_jfif() {
+        for big_endian in [false, true] {
+            let segment = exif_app1(big_endian, &[(ORIENTATION_TAG, 1)]);
+            let mut contents = jpeg(&[&JFIF, &segment]);
+            set_jpeg_orientation(&mut contents, 5).unwrap();
+
+            assert_eq!(&contents[2..4], [0xFF, 0xE0]);
+            assert_eq!(read_tag(&contents, exif::Tag::Orientation), Some(5), "big endian: {}", big_endian);
+        }
+    }
+
+    #[test]
+    fn copies_exif_upright_and_after_jfif() {
+        let segment = exif_app1(false, &[(IMAGE_WIDTH_TAG, 640), (ORIENTATION_TAG, 6)]);
+        let source = jpeg(&[&JFIF, &segment]);
+        let mut encoded = jpeg(&[&JFIF]);
+        copy_exif(&source, &mut encoded);
+
+        let markers: Vec<u8> = jpeg_segments(&encoded).iter().map(|&(marker, _, _)| marker).collect();
+        assert_eq!(markers, [0xE0, 0xE1]);
+        assert_eq!(read_tag(&encoded, exif::Tag::Orientation), Some(1));
+        assert_eq!(read_tag(&encoded, exif::Tag::ImageWidth), Some(640));
+    }
+
+    #[test]
+    fn rejects_broken_exif_data() {
+        let mut garbage_offset = exif_app1(true, &[(ORIENTATION_TAG, 1)]);
+        garbage_offset[Tiff::START + 4..Tiff::START + 8].copy_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+        let mut too_many_entries = exif_app1(false, &[(IMAGE_WIDTH_TAG, 640)]);
+        too_many_entries[Tiff::START + 8..Tiff::START + 10].copy_from_slice(&50u16.to_le_bytes());
+        let mut unknown_byte_order = exif_app1(false, &[(ORIENTATION_TAG, 1)]);
+        unknown_byte_order[Tiff::START..Tiff::START + 2].copy_from_slice(b"XX");
+        let truncated = exif_app1(false, &[(ORIENTATION_TAG, 1)])[..Tiff::START + 6].to_vec();
+
+        for segment in [garbage_offset, too_many_entries, unknown_byte_order, truncated] {
+            assert!(!set_segment_orientation(&mut segment.clone(), 6));
+            assert!(!add_segment_orientation(&mut segment.clone(), 6));
+            let mut contents = jpeg(&[&segment]);
+            let original = contents.clone();
+            assert!(set_jpeg_orientation(&mut contents, 6).is_err());
+            assert_eq!(contents, original);
+        }
+    }
+
+    #[test]
+    fn leaves_other_files_alone() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let mut contents = png.clone();
+        assert_eq!(set_jpeg_orientation(&mut contents, 6), Err(String::from("not a JPEG file")));
+        assert_eq!(contents, png);
+        assert!(jpeg_segments(&png).is_empty());
+
+        let mut encoded = jpeg(&[]);
+        let original = encoded.clone();
+        copy_exif(&png, &mut encoded);
+        assert_eq!(encoded, original);
+    }
+
+    /// An 8×4 image whose pixels all differ, so any wrong turn or mirror shows.
+    fn test_image() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 4, |x, y| Rgba([x as u8, y as u8, (x * 4 + y) as u8, 255])))
+    }
+
+    #[test]
+    fn composes_every_orientation_with_every_transform() {
+        let image = test_image();
+        for orientation in 1..=8 {
+            for transform in ALL {
+                let expected = transform.apply(apply_orientation(image.clone(), Some(orientation)));
+                let composed = transform.apply_to_orientation(orientation);
+                let actual = apply_orientation(image.clone(), Some(composed));
+                assert_eq!(actual.to_rgba8(), expected.to_rgba8(), "{:?} after orientation {}", transform, orientation);
+            }
+        }
+    }
+
+    #[test]
+    fn reframes_edits_for_every_orientation_and_transform() {
+        let crop = CropRect { x: 0.25, y: 0.25, width: 0.5, height: 0.5 };
+        let image = test_image();
+        for orientation in 1..=8 {
+            let upright = apply_orientation(image.clone(), Some(orientation));
+            for quarter_turns in 0..4 {
+                let ops = [EditOp::Crop(crop), EditOp::Rotate { quarter_turns }];
+                for transform in ALL {
+                    let expected = transform.apply(render(upright.clone(), &ops).unwrap());
+                    let actual = render(transform.apply(upright.clone()), &transform.apply_to_edits(&ops)).unwrap();
+                    assert_eq!(
+                        actual.to_rgba8(),
+                        expected.to_rgba8(),
+                        "{:?} of orientation {} with {} quarter turns",
+                        transform,
+                        orientation,
+                        quarter_turns
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mirrors_straightening_only_for_flips() {
+        let ops = [EditOp::Straighten { degrees: 3.0 }];
+        assert_eq!(Transform::RotateRight.apply_to_edits(&ops), ops);
+        assert_eq!(Transform::RotateLeft.apply_to_edits(&ops), ops);
+        assert_eq!(Transform::FlipHorizontal.apply_to_edits(&ops), [EditOp::Straighten { degrees: -3.0 }]);
+        assert_eq!(Transform::FlipVertical.apply_to_edits(&ops), [EditOp::Straighten { degrees: -3.0 }]);
+    }
+
+    #[test]
+    fn undoes_every_transform_with_its_inverse() {
+        let image = test_image();
+        for transform in ALL {
+            let turned_back = transform.inverse().apply(transform.apply(image.clone()));
+            assert_eq!(turned_back.to_rgba8(), image.to_rgba8(), "{:?}", transform);
+        }
+    }
+
+    #[test]
+    fn turns_files_back_when_one_cannot_be_transformed() {
+        let dir = std::env::temp_dir().join(format!("poer-transform-all-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("first.jpg");
+        let original = jpeg(&[&exif_app1(false, &[(ORIENTATION_TAG, 1)])]);
+        fs::write(&path, &original).unwrap();
+
+        let paths = vec![path.clone(), dir.join("missing.jpg")];
+        let result = iced::futures::executor::block_on(transform_all(paths, Transform::RotateRight, TransformMode::Lossless));
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path).unwrap(), original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,380 @@
+use iced::widget::image::Handle;
+use iced::{Size, Vector};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::app::crop_view::{AspectRatio, FULL_CROP};
+use crate::app::edit::{render, CropRect, EditOp};
+use crate::app::exif_data::apply_orientation;
+use crate::app::histogram::{Clipping, Histogram};
+use crate::app::photo_loader::PhotoId;
+use crate::app::photo_view::Zoom;
+use crate::app::preset::Preset;
+use crate::app::tone::{CurveChannel, Levels};
+
+/// Longest side of the proxy that edits are previewed on.
+const PROXY_SIZE: u32 = 2048;
+/// Side of the region shown in the loupe.
+pub const LOUPE_SIZE: u32 = 208;
+/// Pixels rendered around the loupe region so filters near its edges see their neighbours.
+const LOUPE_MARGIN: u32 = 16;
+/// Longest side of the preset thumbnails.
+const PRESET_THUMBNAIL_SIZE: u32 = 96;
+
+/// A decoded, upright copy of a photo ready to be shown in the viewer.
+#[derive(Clone, Debug)]
+pub struct Preview {
+    pub handle: Handle,
+    pub size: Size,
+    pub histogram: Arc<Histogram>,
+    /// Overlays marking the pixels clipped to black and to white, if they were asked
+    /// for and there are any.
+    pub shadow_clipping: Option<Handle>,
+    pub highlight_clipping: Option<Handle>,
+}
+
+/// State of the full-size photo viewer.
+pub struct ViewerState {
+    pub photo: PhotoId,
+    /// Upright, downscaled copy of the original that edits are previewed on.
+    pub proxy: Option<Arc<DynamicImage>>,
+    /// How many times larger the original is than the proxy; zoom levels are relative
+    /// to the original.
+    pub proxy_scale: f32,
+    pub preview: Option<Preview>,
+    /// Whether a preview is being rendered, and whether the edits changed since it started.
+    pub rendering: bool,
+    pub render_pending: bool,
+    pub error: Option<String>,
+    pub zoom: Zoom,
+    pub offset: Vector,
+    /// Set while the crop tool is open.
+    pub crop: Option<CropSession>,
+    /// Channel shown in the tone curve editor.
+    pub curve_channel: CurveChannel,
+    /// Levels being adjusted in the levels dialog, previewed but not yet applied.
+    pub levels: Option<Levels>,
+    /// Upright original at full resolution, decoded while the loupe is open or the
+    /// photo is zoomed in past the proxy.
+    pub original: Option<Arc<DynamicImage>>,
+    pub loading_original: bool,
+    /// The photo rendered at full resolution, shown while zoomed in past the proxy.
+    pub full_view: Option<FullView>,
+    pub full_view_rendering: bool,
+    pub loupe: Option<Loupe>,
+    /// The photo rendered with each preset, by preset name.
+    pub preset_thumbnails: Vec<(String, Handle)>,
+}
+
+/// The edited photo at full resolution.
+#[derive(Clone, Debug)]
+pub struct FullView {
+    /// Original and edits the view was rendered from.
+    pub source: Arc<DynamicImage>,
+    pub edits: Vec<EditOp>,
+    pub handle: Handle,
+}
+
+/// A region of the edited photo shown at 100%, where sharpening and noise reduction can be judged.
+pub struct Loupe {
+    /// Centre of the region, in fractions of the edited photo.
+    pub center: (f32, f32),
+    pub patch: Option<LoupePatch>,
+    pub image: Option<Handle>,
+    /// Why the region could not be shown, if it could not.
+    pub error: Option<String>,
+    pub rendering: bool,
+    pub render_pending: bool,
+}
+
+impl Default for Loupe {
+    fn default() -> Self {
+        Loupe {
+            center: (0.5, 0.5),
+            patch: None,
+            image: None,
+            error: None,
+            rendering: false,
+            render_pending: false,
+        }
+    }
+}
+
+/// The loupe region and its margin rendered with every edit but the detail ones, kept so
+/// detail changes only need to filter this small patch.
+#[derive(Clone, Debug)]
+pub struct LoupePatch {
+    pub image: Arc<DynamicImage>,
+    /// Original, edits and centre the patch was rendered for.
+    pub source: Arc<DynamicImage>,
+    pub edits: Vec<EditOp>,
+    pub center: (f32, f32),
+    /// Where the region lies in the edited photo, in fractions of its size.
+    pub region: CropRect,
+    /// Where the region lies in the patch, in pixels: x, y, width and height.
+    view: (u32, u32, u32, u32),
+}
+
+impl LoupePatch {
+    /// Whether the patch was rendered from `original` around `center` with `edits`,
+    /// leaving the detail ones aside.
+    pub fn matches(&self, original: &Arc<DynamicImage>, edits: &[EditOp], center: (f32, f32)) -> bool {
+        Arc::ptr_eq(&self.source, original)
+            && self.center == center
+            && self.edits.iter().eq(edits.iter().filter(|op| !op.is_detail()))
+    }
+}
+
+/// The crop and straighten settings being adjusted in the crop tool.
+///
+/// The tool works on the topmost straighten and crop operations of the edit stack;
+/// everything else is the base the preview is rendered from.
+pub struct CropSession {
+    pub base: Vec<EditOp>,
+    pub rect: CropRect,
+    pub straighten: f32,
+    pub ratio: AspectRatio,
+    pub custom_width: String,
+    pub custom_height: String,
+}
+
+impl CropSession {
+    /// Starts editing the crop and straighten at the top of `ops`, if any.
+    ///
+    /// Tonal and detail edits above them are looked past, since they do not depend on the framing.
+    pub fn new(ops: &[EditOp]) -> Self {
+        let mut base = ops.to_vec();
+        let mut rect = FULL_CROP;
+        let mut straighten = 0.0;
+        let mut index = base.len();
+        while index > 0 {
+            index -= 1;
+            match base[index] {
+                EditOp::Adjust(_)
+                | EditOp::Curves(_)
+                | EditOp::Levels(_)
+                | EditOp::Sharpen(_)
+                | EditOp::ReduceNoise(_)
+                | EditOp::Lut { .. } => continue,
+                EditOp::Crop(crop) if rect == FULL_CROP && straighten == 0.0 => rect = crop,
+                EditOp::Straighten { degrees } => straighten = degrees,
+                _ => break,
+            }
+            base.remove(index);
+            if straighten != 0.0 {
+                break;
+            }
+        }
+
+        CropSession {
+            base,
+            rect,
+            straighten,
+            ratio: AspectRatio::Free,
+            custom_width: String::new(),
+            custom_height: String::new(),
+        }
+    }
+
+    /// Width over height entered for the custom ratio, if both parse.
+    pub fn custom_ratio(&self) -> Option<f32> {
+        let width = self.custom_width.trim().parse::<f32>().ok()?;
+        let height = self.custom_height.trim().parse::<f32>().ok()?;
+        Some(width / height)
+    }
+
+    /// Ratio the crop is locked to for an image of `image_size`.
+    pub fn ratio_value(&self, image_size: Size) -> Option<f32> {
+        self.ratio.value(image_size, self.custom_ratio())
+    }
+
+    /// Edits to render the preview from: straightened, but not yet cropped.
+    pub fn preview_ops(&self) -> Vec<EditOp> {
+        let mut ops = self.base.clone();
+        if self.straighten != 0.0 {
+            ops.push(EditOp::Straighten { degrees: self.straighten });
+        }
+        ops
+    }
+
+    /// The full edit stack with the crop applied.
+    pub fn ops(&self) -> Vec<EditOp> {
+        let mut ops = self.preview_ops();
+        if self.rect != FULL_CROP {
+            ops.push(EditOp::Crop(self.rect));
+        }
+        ops
+    }
+}
+
+impl ViewerState {
+    pub fn new(photo: PhotoId) -> Self {
+        ViewerState {
+            photo,
+            proxy: None,
+            proxy_scale: 1.0,
+            preview: None,
+            rendering: false,
+            render_pending: false,
+            error: None,
+            zoom: Zoom::Fit,
+            offset: Vector::new(0.0, 0.0),
+            crop: None,
+            curve_channel: CurveChannel::Rgb,
+            levels: None,
+            original: None,
+            loading_original: false,
+            full_view: None,
+            full_view_rendering: false,
+            loupe: None,
+            preset_thumbnails: Vec::new(),
+        }
+    }
+
+    /// Whether the photo is shown at a zoom where the proxy would be enlarged, so the
+    /// full resolution rendering is shown instead.
+    pub fn shows_full_resolution(&self) -> bool {
+        self.crop.is_none() && matches!(self.zoom, Zoom::Scale(scale) if scale * self.proxy_scale >= 1.0)
+    }
+}
+
+/// Decodes the photo at `path`, turns it upright according to its EXIF orientation and
+/// scales it down to a size that edits can be rendered on interactively. Also returns
+/// how many times larger the original is.
+pub async fn load_proxy(path: PathBuf, orientation: Option<u16>) -> Result<(Arc<DynamicImage>, f32), String> {
+    let image = apply_orientation(image::open(&path).map_err(|err| err.to_string())?, orientation);
+    if image.width() > PROXY_SIZE || image.height() > PROXY_SIZE {
+        let proxy = image.resize(PROXY_SIZE, PROXY_SIZE, FilterType::Triangle);
+        let scale = image.width() as f32 / proxy.width() as f32;
+        Ok((Arc::new(proxy), scale))
+    } else {
+        Ok((Arc::new(image), 1.0))
+    }
+}
+
+/// Decodes the photo at `path` at full resolution and turns it upright.
+pub async fn load_original(path: PathBuf, orientation: Option<u16>) -> Result<Arc<DynamicImage>, String> {
+    let image = image::open(&path).map_err(|err| err.to_string())?;
+    Ok(Arc::new(apply_orientation(image, orientation)))
+}
+
+/// Applies `edits` to a copy of the proxy. Detail edits are left out, since they are
+/// tuned for full resolution and are judged in the loupe instead. Only the clipping
+/// overlays in `overlays` are built.
+pub async fn render_preview(proxy: Arc<DynamicImage>, edits: Vec<EditOp>, overlays: Vec<Clipping>) -> Result<Preview, String> {
+    let edits: Vec<EditOp> = edits.into_iter().filter(|op| !op.is_detail()).collect();
+    let rgba = render(DynamicImage::clone(&proxy), &edits)?.into_rgba8();
+    let size = Size::new(rgba.width() as f32, rgba.height() as f32);
+    let histogram = Arc::new(Histogram::of(&rgba));
+    let mask = |clipping: Clipping| {
+        overlays
+            .contains(&clipping)
+            .then(|| clipping.mask(&rgba))
+            .flatten()
+            .map(|mask| Handle::from_pixels(mask.width(), mask.height(), mask.into_raw()))
+    };
+    let shadow_clipping = mask(Clipping::Shadows);
+    let highlight_clipping = mask(Clipping::Highlights);
+
+    Ok(Preview {
+        handle: Handle::from_pixels(rgba.width(), rgba.height(), rgba.into_raw()),
+        size,
+        histogram,
+        shadow_clipping,
+        highlight_clipping,
+    })
+}
+
+/// Applies `edits`, detail ones included, to a copy of the full resolution original.
+pub async fn render_full_view(original: Arc<DynamicImage>, edits: Vec<EditOp>) -> Result<FullView, String> {
+    let rgba = render(DynamicImage::clone(&original), &edits)?.into_rgba8();
+    let handle = Handle::from_pixels(rgba.width(), rgba.height(), rgba.into_raw());
+    Ok(FullView { source: original, edits, handle })
+}
+
+/// Renders a small copy of the proxy with `edits` and each of `presets` applied on top,
+/// leaving out the presets that fail to render.
+pub async fn render_preset_thumbnails(
+    proxy: Arc<DynamicImage>,
+    edits: Vec<EditOp>,
+    presets: Vec<Preset>,
+) -> Vec<(String, Handle)> {
+    let small = proxy.thumbnail(PRESET_THUMBNAIL_SIZE, PRESET_THUMBNAIL_SIZE);
+    presets
+        .into_iter()
+        .filter_map(|preset| {
+            let ops: Vec<EditOp> = preset.apply_to(&edits).into_iter().filter(|op| !op.is_detail()).collect();
+            let rgba = match render(small.clone(), &ops) {
+                Ok(image) => image.into_rgba8(),
+                Err(err) => {
+                    eprintln!("Failed to render preset {}: {}", preset.name, err);
+                    return None;
+                }
+            };
+            Some((preset.name, Handle::from_pixels(rgba.width(), rgba.height(), rgba.into_raw())))
+        })
+        .collect()
+}
+
+/// Renders the loupe region around `center` of the original with `edits` applied.
+///
+/// `patch` is reused if it was rendered for the same centre and the same edits apart
+/// from the detail ones, so only those need to be applied again.
+pub async fn render_loupe(
+    original: Arc<DynamicImage>,
+    patch: Option<LoupePatch>,
+    edits: Vec<EditOp>,
+    center: (f32, f32),
+) -> Result<(LoupePatch, Handle), String> {
+    let (detail, base): (Vec<EditOp>, Vec<EditOp>) = edits.into_iter().partition(|op| op.is_detail());
+    let patch = match patch {
+        Some(patch) if patch.matches(&original, &base, center) => patch,
+        _ => cut_patch(&original, base, center)?,
+    };
+
+    let (x, y, width, height) = patch.view;
+    let rgba = render(DynamicImage::clone(&patch.image), &detail)?
+        .crop_imm(x, y, width, height)
+        .into_rgba8();
+    let handle = Handle::from_pixels(rgba.width(), rgba.height(), rgba.into_raw());
+    Ok((patch, handle))
+}
+
+/// Renders the loupe region and its margin. Framing edits move pixels around, so with
+/// those the whole original is rendered first; every other edit works pixel by pixel
+/// and is only applied to the patch.
+fn cut_patch(original: &Arc<DynamicImage>, edits: Vec<EditOp>, (center_x, center_y): (f32, f32)) -> Result<LoupePatch, String> {
+    let framed = edits.iter().any(EditOp::is_framing);
+    let edited = framed.then(|| render(DynamicImage::clone(original), &edits)).transpose()?;
+    let source: &DynamicImage = edited.as_ref().unwrap_or(original);
+    let (width, height) = (source.width(), source.height());
+    let (region_width, region_height) = (LOUPE_SIZE.min(width), LOUPE_SIZE.min(height));
+    let left = |center: f32, size: u32, region: u32| {
+        ((center.clamp(0.0, 1.0) * size as f32) as u32).saturating_sub(region / 2).min(size - region)
+    };
+    let (x, y) = (left(center_x, width, region_width), left(center_y, height, region_height));
+
+    let patch_x = x.saturating_sub(LOUPE_MARGIN);
+    let patch_y = y.saturating_sub(LOUPE_MARGIN);
+    let patch_width = (x + region_width + LOUPE_MARGIN).min(width) - patch_x;
+    let patch_height = (y + region_height + LOUPE_MARGIN).min(height) - patch_y;
+
+    let patch = source.crop_imm(patch_x, patch_y, patch_width, patch_height);
+    let image = if framed { patch } else { render(patch, &edits)? };
+
+    Ok(LoupePatch {
+        image: Arc::new(image),
+        source: Arc::clone(original),
+        edits,
+        center: (center_x, center_y),
+        region: CropRect {
+            x: x as f32 / width as f32,
+            y: y as f32 / height as f32,
+            width: region_width as f32 / width as f32,
+            height: region_height as f32 / height as f32,
+        },
+        view: (x - patch_x, y - patch_y, region_width, region_height),
+    })
+}
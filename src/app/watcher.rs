@@ -0,0 +1,227 @@
+use iced::futures::channel::mpsc::{self as async_mpsc, UnboundedSender};
+use iced::futures::{future, SinkExt, StreamExt};
+use iced::subscription::{self, Subscription};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+use crate::app::library::LibraryRoot;
+use crate::app::photo_loader::{is_supported, probe_photo, Photo};
+
+/// How long the filesystem has to stay quiet before a batch of changes is reported.
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound on how long changes are held back while events keep arriving.
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+    Added(PathBuf),
+    Removed(PathBuf),
+    Modified(PathBuf),
+}
+
+/// Watches the enabled library roots and reports batches of changed paths.
+///
+/// Added and modified paths are either supported image files or directories
+/// whose contents should be indexed; removed paths may be either as well.
+pub fn watch(roots: Vec<LibraryRoot>) -> Subscription<Vec<WatchEvent>> {
+    subscription::channel(("library-watch", roots.clone()), 100, move |mut output| async move {
+        let (sender, mut receiver) = async_mpsc::unbounded();
+        let (raw_sender, raw_events) = mpsc::channel();
+
+        // The watcher stops as soon as it is dropped, so keep it alive alongside the stream.
+        let _watcher = match notify::recommended_watcher(raw_sender) {
+            Ok(mut watcher) => {
+                for root in roots.iter().filter(|root| root.enabled) {
+                    let mode = match root.max_depth {
+                        Some(0) => RecursiveMode::NonRecursive,
+                        _ => RecursiveMode::Recursive,
+                    };
+                    if let Err(err) = watcher.watch(&root.path, mode) {
+                        eprintln!("Failed to watch {}: {}", root.path.display(), err);
+                    }
+                }
+                let roots = roots.clone();
+                thread::spawn(move || collect_changes(raw_events, &roots, sender));
+                Some(watcher)
+            }
+            Err(err) => {
+                eprintln!("Failed to start the filesystem watcher: {}", err);
+                None
+            }
+        };
+
+        while let Some(changes) = receiver.next().await {
+            if output.send(changes).await.is_err() {
+                break;
+            }
+        }
+
+        future::pending().await
+    })
+}
+
+/// Probes the given files, walking any directories among them no deeper than the
+/// library `roots` reach, off the UI thread.
+pub async fn probe_changes(paths: Vec<PathBuf>, roots: Vec<LibraryRoot>) -> Vec<Photo> {
+    let mut photos = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut walker = WalkDir::new(&path);
+            if let Some(depth) = remaining_depth(&path, &roots) {
+                walker = walker.max_depth(depth);
+            }
+            photos.extend(
+                walker
+                    .into_iter()
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| is_supported(entry.path()))
+                    .filter_map(|entry| probe_photo(entry.into_path())),
+            );
+        } else if let Some(photo) = probe_photo(path) {
+            photos.push(photo);
+        }
+    }
+    photos
+}
+
+/// How many levels below `dir` the enabled `roots` containing it still index, or `None`
+/// when one of them has no depth limit.
+fn remaining_depth(dir: &Path, roots: &[LibraryRoot]) -> Option<usize> {
+    let mut remaining = 0;
+    for root in roots.iter().filter(|root| root.enabled) {
+        let Ok(relative) = dir.strip_prefix(&root.path) else {
+            continue;
+        };
+        let depth = root.max_depth?;
+        remaining = remaining.max((depth + 1).saturating_sub(relative.components().count()));
+    }
+    Some(remaining)
+}
+
+fn collect_changes(
+    raw_events: mpsc::Receiver<notify::Result<notify::Event>>,
+    roots: &[LibraryRoot],
+    output: UnboundedSender<Vec<WatchEvent>>,
+) {
+    let mut pending: HashMap<PathBuf, WatchEvent> = HashMap::new();
+    let mut first_change = Instant::now();
+
+    loop {
+        let next = if pending.is_empty() {
+            raw_events.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected)
+        } else {
+            raw_events.recv_timeout(SETTLE_DELAY)
+        };
+
+        let settled = matches!(next, Err(mpsc::RecvTimeoutError::Timeout));
+        match next {
+            Ok(Ok(event)) => {
+                if pending.is_empty() {
+                    first_change = Instant::now();
+                }
+                for change in translate(event) {
+                    if is_relevant(&change, roots) {
+                        pending.insert(change_path(&change).to_path_buf(), change);
+                    }
+                }
+            }
+            Ok(Err(_)) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        if !pending.is_empty() && (settled || first_change.elapsed() >= MAX_DELAY) {
+            let changes = pending.drain().map(|(_, change)| change).collect();
+            if output.unbounded_send(changes).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn translate(event: notify::Event) -> Vec<WatchEvent> {
+    let mut paths = event.paths.into_iter();
+    match event.kind {
+        EventKind::Create(_) => paths.map(WatchEvent::Added).collect(),
+        EventKind::Remove(_) => paths.map(WatchEvent::Removed).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => paths.map(WatchEvent::Removed).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => paths.map(WatchEvent::Added).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let from = paths.next().map(WatchEvent::Removed);
+            let to = paths.next().map(WatchEvent::Added);
+            from.into_iter().chain(to).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => paths
+            .map(|path| if path.exists() { WatchEvent::Added(path) } else { WatchEvent::Removed(path) })
+            .collect(),
+        EventKind::Modify(_) => paths.map(WatchEvent::Modified).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn change_path(change: &WatchEvent) -> &Path {
+    match change {
+        WatchEvent::Added(path) | WatchEvent::Removed(path) | WatchEvent::Modified(path) => path,
+    }
+}
+
+fn is_relevant(change: &WatchEvent, roots: &[LibraryRoot]) -> bool {
+    let path = change_path(change);
+    let within_depth = roots.iter().any(|root| {
+        path.strip_prefix(&root.path).is_ok_and(|relative| {
+            root.max_depth.is_none_or(|depth| relative.components().count() <= depth + 1)
+        })
+    });
+
+    match change {
+        WatchEvent::Removed(_) => within_depth,
+        WatchEvent::Added(_) | WatchEvent::Modified(_) => {
+            within_depth && (is_supported(path) || path.is_dir())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(path: &str, max_depth: Option<usize>) -> LibraryRoot {
+        LibraryRoot { path: PathBuf::from(path), enabled: true, max_depth }
+    }
+
+    #[test]
+    fn counts_the_levels_left_below_a_directory() {
+        let roots = [root("/photos", Some(1))];
+        assert_eq!(remaining_depth(Path::new("/photos"), &roots), Some(2));
+        assert_eq!(remaining_depth(Path::new("/photos/2020"), &roots), Some(1));
+        assert_eq!(remaining_depth(Path::new("/photos/2020/june"), &roots), Some(0));
+        assert_eq!(remaining_depth(Path::new("/photos/2020/june/raw"), &roots), Some(0));
+    }
+
+    #[test]
+    fn walks_everything_below_a_root_without_a_limit() {
+        let roots = [root("/photos", Some(0)), root("/photos/2020", None)];
+        assert_eq!(remaining_depth(Path::new("/photos/2020/june"), &roots), None);
+        assert_eq!(remaining_depth(Path::new("/photos/2021"), &roots), Some(0));
+    }
+
+    #[test]
+    fn takes_the_deepest_of_nested_roots() {
+        let roots = [root("/photos", Some(0)), root("/photos/2020", Some(2))];
+        assert_eq!(remaining_depth(Path::new("/photos/2020/june"), &roots), Some(2));
+    }
+
+    #[test]
+    fn ignores_disabled_and_unrelated_roots() {
+        let mut disabled = root("/photos", None);
+        disabled.enabled = false;
+        let roots = [disabled, root("/other", None)];
+        assert_eq!(remaining_depth(Path::new("/photos/2020"), &roots), Some(0));
+    }
+}